@@ -0,0 +1,189 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use clap::ValueEnum;
+use image::DynamicImage;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{app_error::AppError, args::Command};
+
+
+/// Largest PNG taken over the socket, far more than any panel needs
+const MAX_PNG_SIZE: usize = 8 * 1024 * 1024;
+/// How long a client can keep the API waiting for the rest of a request
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+
+/// Local control API.
+///
+/// Requests and responses are JSON, one per line:
+///
+/// ```text
+/// {"action": "view", "view": "tasks"}
+/// {"action": "text", "text": "Dinner is ready"}
+/// {"action": "png", "path": "/tmp/chart.png"}
+/// {"action": "png", "size": 1234}   followed by 1234 bytes of PNG
+/// {"action": "refresh"}
+/// {"action": "clear"}
+/// {"action": "status"}
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+enum Request {
+    View { view: String },
+    Text { text: String },
+    Png { path: Option<PathBuf>, size: Option<usize> },
+    Refresh,
+    Clear,
+    Status,
+}
+
+
+#[derive(Serialize, Debug)]
+struct Response {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<Status>,
+}
+
+
+/// What the display loop has to do
+pub enum Action {
+    View(Command),
+    Text(String),
+    Picture(DynamicImage),
+    /// Redraw the current content with a full refresh
+    Refresh,
+    Clear,
+}
+
+
+/// Shared by the display loop with the API
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Status {
+    /// What is on the panel
+    pub view: String,
+    /// When the panel was last refreshed, RFC 3339
+    pub last_refresh: Option<String>,
+    pub last_error: Option<String>,
+}
+
+
+/// Listen on the Unix socket in the background, passing actions to `actions`
+pub fn serve(path: &Path, actions: Sender<Action>, status: Arc<Mutex<Status>>) -> Result<(), AppError> {
+    // A socket left over from the previous run would fail the bind
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    info!("Listening on {}", path.display());
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.map_err(AppError::from).and_then(|stream| {
+                handle(stream, &actions, &status)
+            });
+
+            if let Err(e) = result {
+                warn!("API connection failed: {}", e);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+
+fn handle(stream: UnixStream, actions: &Sender<Action>, status: &Arc<Mutex<Status>>) -> Result<(), AppError> {
+    // Connections are served one at a time, a stalled client would block the others
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        let response = match respond(&line, &mut reader, actions, status) {
+            Ok(status) => Response { ok: true, error: None, status },
+            Err(e) => Response { ok: false, error: Some(e.to_string()), status: None },
+        };
+
+        let response = serde_json::to_string(&response).map_err(|e| AppError::Data(e.to_string()))?;
+        writeln!(writer, "{}", response)?;
+
+        line.clear();
+    }
+
+    Ok(())
+}
+
+
+fn respond(line: &str, reader: &mut impl Read, actions: &Sender<Action>, status: &Arc<Mutex<Status>>) -> Result<Option<Status>, AppError> {
+    let request: Request = serde_json::from_str(line)
+        .map_err(|e| AppError::Data(format!("Wrong request: {}", e)))?;
+
+    info!("API request: {:?}", request);
+
+    let action = match request {
+        Request::View { view } => Action::View(
+            Command::from_str(&view, true).map_err(|e| AppError::Data(format!("Wrong view: {}", e)))?
+        ),
+        Request::Text { text } => Action::Text(text),
+        Request::Png { path: Some(path), .. } => Action::Picture(
+            image::open(path).map_err(|e| AppError::Data(format!("Wrong picture: {}", e)))?
+        ),
+        Request::Png { path: None, size: Some(size) } => {
+            if size > MAX_PNG_SIZE {
+                return Err(AppError::Data(format!("Picture of {} bytes is over the limit of {}", size, MAX_PNG_SIZE)));
+            }
+
+            let mut data = vec![0; size];
+            reader.read_exact(&mut data)?;
+            Action::Picture(
+                image::load_from_memory(&data).map_err(|e| AppError::Data(format!("Wrong picture: {}", e)))?
+            )
+        },
+        Request::Png { path: None, size: None } => {
+            return Err(AppError::Data("Either 'path' or 'size' of the picture is required".to_string()));
+        },
+        Request::Refresh => Action::Refresh,
+        Request::Clear => Action::Clear,
+        Request::Status => {
+            let status = status.lock().map_err(|e| AppError::Data(e.to_string()))?;
+            return Ok(Some(status.clone()));
+        },
+    };
+
+    actions.send(action).map_err(|_| AppError::Data("The display loop has stopped".to_string()))?;
+
+    Ok(None)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn picture_too_large() {
+        let (actions, orders) = mpsc::channel();
+        let status = Arc::new(Mutex::new(Status::default()));
+
+        let line = format!("{{\"action\": \"png\", \"size\": {}}}", usize::MAX);
+        let error = respond(&line, &mut std::io::empty(), &actions, &status).unwrap_err();
+
+        assert!(error.to_string().contains("over the limit"), "{}", error);
+        assert!(orders.try_recv().is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use crate::driver::DriverError;
 
 
@@ -8,15 +10,55 @@ pub enum AppError {
     Io(std::io::Error),
     Utf8(std::string::FromUtf8Error),
     Regex(regex::Error),
+    /// The vault, or a file in it, can't be read; paths are relative to the vault
+    Vault { path: Option<PathBuf>, message: String },
+    /// A file describing what to show can't be used
+    Config { path: PathBuf, message: String },
+    /// A program run for a view didn't work
+    Command { command: String, message: String },
+}
+
+impl AppError {
+    /// What went wrong in a few words, for the error panel
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::Display(_) => "Display failed",
+            Self::Data(_) | Self::Utf8(_) | Self::Regex(_) => "Data invalid",
+            Self::Io(_) => "IO failed",
+            Self::Vault { .. } => "Vault missing",
+            Self::Config { .. } => "Config invalid",
+            Self::Command { .. } => "Command failed",
+        }
+    }
 }
 
 impl std::fmt::Display for AppError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            Self::Display(e) => write!(f, "Display: {}", e),
+            Self::Data(e) => write!(f, "Data: {}", e),
+            Self::Io(e) => write!(f, "IO: {}", e),
+            Self::Utf8(e) => write!(f, "UTF-8: {}", e),
+            Self::Regex(e) => write!(f, "Regex: {}", e),
+            Self::Vault { path: None, message } => write!(f, "Vault: {}", message),
+            Self::Vault { path: Some(path), message } => write!(f, "Vault: {} ({})", message, path.display()),
+            Self::Config { path, message } => write!(f, "Config: {} in {}", message, path.display()),
+            Self::Command { command, message } => write!(f, "Command: '{}' {}", command, message),
+        }
     }
 }
 
-impl std::error::Error for AppError {}
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Display(e) => Some(e),
+            Self::Io(e) => Some(e),
+            Self::Utf8(e) => Some(e),
+            Self::Regex(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl From<DriverError> for AppError {
     fn from(value: DriverError) -> Self {
@@ -1,4 +1,12 @@
+use std::ops::Range;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
 use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+
+use crate::vault::Status;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -7,28 +15,177 @@ pub struct Args {
     #[arg(short, long)]
     pub repeat: bool,
 
+    /// Keep running and take orders over a Unix socket at this path
+    #[arg(long, env = "SOCKET")]
+    pub socket: Option<PathBuf>,
+
+    /// Seconds between refreshes when taking orders over the socket
+    #[arg(long, env = "INTERVAL", default_value_t = 300)]
+    pub interval: u64,
+
     #[arg(short, long, value_enum, default_value_t)]
     pub mode: DisplayMode,
 
+    /// Model of the connected e-Paper panel
+    #[arg(short, long, value_enum, env = "PANEL", default_value_t)]
+    pub panel: PanelKind,
+
     /// Output the image into a file as well as to the screen
     #[arg(short, long)]
     pub debug: bool,
 
+    /// Print the image to the terminal instead of the screen, as the panel would show it
+    #[arg(long, value_enum)]
+    pub preview: Option<Preview>,
+
+    /// Show the Wi-Fi, when the vault last changed, the time of the refresh and the mode along the bottom of every view
+    #[arg(long, env = "STATUS_BAR")]
+    pub status_bar: bool,
+
+    /// Warn when the vault sync looks stalled: the unit not running, no changes for a while, conflicts
+    #[arg(long, env = "SYNC_CHECK")]
+    pub sync_check: bool,
+
+    /// Systemd unit syncing the vault
+    #[arg(long, env = "SYNC_UNIT", default_value = "obsidian-sync")]
+    pub sync_unit: String,
+
+    /// Hours without any change in the vault after which the sync counts as stalled
+    #[arg(long, env = "SYNC_MAX_AGE", default_value_t = 24)]
+    pub sync_max_age: u32,
+
+    /// Waveform files to use in the Fast mode instead of the one from OTP, as `[FROM..TO:]PATH`
+    #[arg(long, env = "LUT_FAST", value_delimiter = ',', allow_hyphen_values = true)]
+    pub lut_fast: Vec<LutArg>,
+
+    /// Waveform files to use in the Grey mode instead of the built-in one, as `[FROM..TO:]PATH`
+    #[arg(long, env = "LUT_GREY", value_delimiter = ',', allow_hyphen_values = true)]
+    pub lut_grey: Vec<LutArg>,
+
+    /// Temperature of the panel in °C, instead of the internal sensor
+    #[arg(long, env = "TEMPERATURE", allow_hyphen_values = true, conflicts_with = "temperature_file")]
+    pub temperature: Option<f32>,
+
+    /// File to read the temperature of the panel from before every refresh, e.g. a hwmon or IIO sensor
+    #[arg(long, env = "TEMPERATURE_FILE")]
+    pub temperature_file: Option<PathBuf>,
+
+    /// What the debug command draws
+    #[arg(long, value_enum, default_value_t)]
+    pub pattern: Pattern,
+
+    /// Picture shown by the image command: PNG, JPEG, BMP or GIF (first frame)
+    #[arg(long, required_if_eq("command", "image"))]
+    pub image: Option<PathBuf>,
+
+    /// How the picture is scaled to the panel
+    #[arg(long, value_enum, default_value_t)]
+    pub fit: Scaling,
+
+    /// Gamma applied to the picture, values above 1 make it brighter
+    #[arg(long, default_value_t = 1.0, value_parser = positive)]
+    pub gamma: f32,
+
+    /// Contrast applied to the picture, values above 1 increase it
+    #[arg(long, default_value_t = 1.0, value_parser = not_negative)]
+    pub contrast: f32,
+
+    /// How the picture is reduced to the greys available in the display mode
+    #[arg(long, value_enum, default_value_t)]
+    pub dither: Dither,
+
+    /// iCalendar files, or directories of them, with events for the calendar view
+    #[arg(long, env = "ICS", value_delimiter = ',')]
+    pub ics: Vec<PathBuf>,
+
+    /// Properties from the frontmatter of today's note shown above the tasks, e.g. `focus,mood`
+    #[arg(long, env = "PROPERTIES", value_delimiter = ',')]
+    pub properties: Vec<String>,
+
+    /// Headings of today's note the tasks view lists tasks from, e.g. `Plan,Work > Today`, all of them when not set
+    #[arg(long, env = "TASK_SECTIONS", value_delimiter = ',')]
+    pub task_sections: Vec<String>,
+
+    /// Statuses of the tasks listed by the tasks view
+    #[arg(long, env = "TASK_STATUSES", value_enum, value_delimiter = ',', default_values_t = [TaskStatus::Todo, TaskStatus::InProgress, TaskStatus::Important, TaskStatus::Question])]
+    pub task_statuses: Vec<TaskStatus>,
+
+    /// Put the tasks of each heading in the order of `--task-statuses`
+    #[arg(long, env = "SORT_BY_STATUS")]
+    pub sort_by_status: bool,
+
+    /// Headings of today's note with their own progress bar in the tasks view, e.g. `Work,Home`
+    #[arg(long, env = "PROGRESS_HEADINGS", value_delimiter = ',')]
+    pub progress_headings: Vec<String>,
+
+    /// How many days after today the agenda view shows
+    #[arg(long, env = "AGENDA_DAYS", default_value_t = 6)]
+    pub agenda_days: u32,
+
+    /// How many days before today the agenda view looks for unchecked tasks
+    #[arg(long, env = "OVERDUE_DAYS", default_value_t = 7)]
+    pub overdue_days: u32,
+
+    /// Inline fields or task names to track in the habits view, e.g. `exercise,water,meditate`
+    #[arg(long, env = "HABITS", value_delimiter = ',')]
+    pub habits: Vec<String>,
+
+    /// How many past daily notes the habits view looks at, today included
+    #[arg(long, env = "HABIT_DAYS", default_value_t = 70)]
+    pub habit_days: u32,
+
+    /// Data sources and widgets shown by the widgets command, see `command::widgets::Config`
+    #[arg(long, env = "WIDGETS", required_if_eq("command", "widgets"))]
+    pub widgets: Option<PathBuf>,
+
+    /// Screen template drawn by the screen command, see `command::screen::Screen`
+    #[arg(long, env = "SCREEN", required_if_eq("command", "screen"))]
+    pub screen: Option<PathBuf>,
+
+    /// Heading of today's note shown by the section command, e.g. `Plan > Morning`
+    #[arg(long, env = "SECTION", required_if_eq("command", "section"))]
+    pub section: Option<String>,
+
+    /// Shell command run by the script command, printing text or a PNG
+    #[arg(long, env = "SCRIPT", required_if_eq("command", "script"))]
+    pub script: Option<String>,
+
+    /// Seconds the script may run before it's stopped
+    #[arg(long, env = "SCRIPT_TIMEOUT", default_value = "10", value_parser = seconds)]
+    pub script_timeout: Duration,
+
+    /// How the text printed by the script is drawn
+    #[arg(long, value_enum, default_value_t)]
+    pub text_style: TextStyle,
+
+    /// Length of a work session in the focus view, in minutes
+    #[arg(long, env = "FOCUS_MINUTES", default_value_t = 25)]
+    pub focus_minutes: u32,
+
+    /// Length of a break in the focus view, in minutes
+    #[arg(long, env = "BREAK_MINUTES", default_value_t = 5)]
+    pub break_minutes: u32,
+
     #[arg(value_enum, default_value_t)]
     pub command: Command,
 }
 
 
-#[derive(ValueEnum, Clone, Copy, Debug)]
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum DisplayMode {
+    #[default]
     Full,
     Fast,
     Grey,
 }
 
-impl Default for DisplayMode {
-    fn default() -> Self {
-        Self::Full
+impl DisplayMode {
+    /// Number of grey levels the mode can show
+    pub fn levels(&self) -> u8 {
+        match self {
+            Self::Full | Self::Fast => 2,
+            Self::Grey => 4,
+        }
     }
 }
 
@@ -44,17 +201,275 @@ impl std::fmt::Display for DisplayMode {
 }
 
 
-#[derive(ValueEnum, Clone, Copy, Debug)]
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
 pub enum Command {
+    #[default]
     Clear,
     Debug,
     Tasks,
     Network,
     Calendar,
+    /// Days of the current month with how many tasks are open
+    Month,
+    /// Unchecked tasks of today and the next days, with the ones left over from the last days
+    Agenda,
+    Image,
+    /// Big digits updated every minute
+    Clock,
+    /// Heatmaps of habits from past daily notes
+    Habits,
+    /// Values from JSON files written by other programs
+    Widgets,
+    /// Regions of text, tasks, clocks, values, pictures and QR codes described in a file
+    Screen,
+    /// Text or PNG printed by a script
+    Script,
+    /// Paragraphs, lists and quotes under a heading of today's note
+    Section,
+    /// Work and break timer on one of today's tasks, driven by the keys of the HAT
+    Focus,
+}
+
+
+/// Waveform file, optionally limited to a temperature band in °C, e.g. `-5..15:luts/grey-cold.txt`
+#[derive(Clone, Debug)]
+pub struct LutArg {
+    pub band: Option<Range<f32>>,
+    pub path: PathBuf,
+}
+
+impl FromStr for LutArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // A band has no slashes, unlike a path like `../luts/grey:cold.txt`
+        let Some((band, path)) = s.split_once(':').filter(|(band, _)| band.contains("..") && !band.contains('/')) else {
+            return Ok(Self { band: None, path: PathBuf::from(s) });
+        };
+
+        let (from, to) = band.split_once("..").unwrap();
+        let bound = |b: &str, default: f32| match b.trim() {
+            "" => Ok(default),
+            b => b.parse::<f32>().map_err(|e| format!("Wrong temperature '{}': {}", b, e)),
+        };
+
+        Ok(Self {
+            band: Some(bound(from, f32::NEG_INFINITY)?..bound(to, f32::INFINITY)?),
+            path: PathBuf::from(path),
+        })
+    }
 }
 
-impl Default for Command {
-    fn default() -> Self {
-        Self::Clear
+
+/// A number above 0
+fn positive(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(n) if n.is_finite() && n > 0.0 => Ok(n),
+        Ok(_) => Err("has to be above 0".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+
+/// A number of 0 or more
+fn not_negative(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(n) if n.is_finite() && n >= 0.0 => Ok(n),
+        Ok(_) => Err("can't be below 0".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+
+/// A time above 0 in seconds
+fn seconds(s: &str) -> Result<Duration, String> {
+    Duration::try_from_secs_f32(positive(s)?).map_err(|e| e.to_string())
+}
+
+
+/// Calibration patterns drawn by the debug command
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum Pattern {
+    /// Frame, axes and a crossed square
+    #[default]
+    Axes,
+    /// One band for each of the 4 grey levels
+    Greys,
+    /// Smooth gradient from black to white
+    Gradient,
+    /// Fine checkerboard next to solid blocks, to spot ghosting
+    Checker,
+}
+
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum TextStyle {
+    /// 12 pixels
+    Small,
+    /// 16 pixels, as in the other views
+    #[default]
+    Normal,
+    /// 24 pixels
+    Large,
+    /// As big as the panel allows, centered, for a number or a word
+    Big,
+}
+
+
+/// Checkbox statuses of tasks, see `vault::Status`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskStatus {
+    /// `[ ]`
+    Todo,
+    /// `[/]`
+    InProgress,
+    /// `[!]`
+    Important,
+    /// `[?]`
+    Question,
+    /// `[>]`, moved to another day
+    Deferred,
+    /// `[-]`
+    Cancelled,
+    /// `[x]`, and any other character
+    Done,
+}
+
+impl TaskStatus {
+    pub fn status(self) -> Status {
+        match self {
+            Self::Todo => Status::Todo,
+            Self::InProgress => Status::InProgress,
+            Self::Important => Status::Important,
+            Self::Question => Status::Question,
+            Self::Deferred => Status::Deferred,
+            Self::Cancelled => Status::Cancelled,
+            Self::Done => Status::Done,
+        }
+    }
+}
+
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Preview {
+    /// Half blocks with 24 bit colours
+    Terminal,
+    /// Plain block characters, one per pixel in the Grey mode
+    Blocks,
+    /// Braille dots, black and white only
+    Braille,
+    /// Sixel graphics
+    Sixel,
+}
+
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scaling {
+    /// Scale to fit entirely, leaving white margins
+    #[default]
+    Fit,
+    /// Scale to cover the whole panel, cropping the overflow
+    Fill,
+    /// Keep the original size, centered
+    Center,
+}
+
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Dither {
+    /// Round to the nearest grey, good for QR codes and diagrams
+    None,
+    /// Error diffusion, good for photos
+    #[default]
+    FloydSteinberg,
+    /// Regular 4x4 pattern
+    Bayer,
+}
+
+
+/// Supported Waveshare e-Paper panels
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PanelKind {
+    /// 2.13 inch V4, 122x250, black and white
+    #[value(name = "2in13-v4")]
+    Epd2in13V4,
+    /// 2.13 inch (B) V4, 122x250, black, white and red
+    #[value(name = "2in13b-v4")]
+    Epd2in13bV4,
+    /// 2.7 inch V2, 176x264, black and white with 4 greys
+    #[default]
+    #[value(name = "2in7-v2")]
+    Epd2in7V2,
+    /// 2.9 inch V2, 128x296, black and white
+    #[value(name = "2in9-v2")]
+    Epd2in9V2,
+    /// 4.2 inch V2, 400x300, black and white
+    #[value(name = "4in2-v2")]
+    Epd4in2V2,
+    /// 4.2 inch (B) V2, 400x300, black, white and red
+    #[value(name = "4in2b-v2")]
+    Epd4in2bV2,
+    /// 7.5 inch V2, 800x480, black and white
+    #[value(name = "7in5-v2")]
+    Epd7in5V2,
+    /// 7.5 inch (B) V2, 800x480, black, white and red
+    #[value(name = "7in5b-v2")]
+    Epd7in5bV2,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lut(s: &str) -> (Option<Range<f32>>, PathBuf) {
+        let arg = LutArg::from_str(s).unwrap();
+        (arg.band, arg.path)
+    }
+
+    #[test]
+    fn lut_args() {
+        assert_eq!(lut("luts/grey.txt"), (None, PathBuf::from("luts/grey.txt")));
+        assert_eq!(lut("-5..15:luts/grey.txt"), (Some(-5.0..15.0), PathBuf::from("luts/grey.txt")));
+        assert_eq!(lut("10..:../luts/hot.txt"), (Some(10.0..f32::INFINITY), PathBuf::from("../luts/hot.txt")));
+        assert_eq!(lut("..0:cold.txt"), (Some(f32::NEG_INFINITY..0.0), PathBuf::from("cold.txt")));
+
+        // Paths with both `:` and `..`
+        assert_eq!(lut("../luts/grey:cold.txt"), (None, PathBuf::from("../luts/grey:cold.txt")));
+        assert_eq!(lut("/mnt/a..b/c:d.txt"), (None, PathBuf::from("/mnt/a..b/c:d.txt")));
+
+        assert!(LutArg::from_str("5..x:grey.txt").is_err());
+    }
+
+    #[test]
+    fn picture_tones() {
+        assert_eq!(positive("2.2"), Ok(2.2));
+        assert!(positive("0").is_err());
+        assert!(positive("-1").is_err());
+        assert!(positive("NaN").is_err());
+
+        assert_eq!(not_negative("0"), Ok(0.0));
+        assert!(not_negative("-0.5").is_err());
+        assert!(not_negative("inf").is_err());
+    }
+
+    #[test]
+    fn task_statuses() {
+        let status = |s: &str| TaskStatus::from_str(s, false).map(TaskStatus::status);
+
+        assert_eq!(status("in-progress"), Ok(Status::InProgress));
+        assert_eq!(status("done"), Ok(Status::Done));
+        assert!(status("open").is_err());
+    }
+
+    #[test]
+    fn script_timeout() {
+        assert_eq!(seconds("0.5"), Ok(Duration::from_millis(500)));
+        assert!(seconds("0").is_err());
+        assert!(seconds("-1").is_err());
+        assert!(seconds("NaN").is_err());
+        assert!(seconds("1e30").is_err());
     }
 }
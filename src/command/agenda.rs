@@ -0,0 +1,82 @@
+use chrono::{Duration, NaiveDate};
+use cosmic_text::Color;
+use imageproc::rect::Rect;
+
+use crate::{driver::DisplayImage, render, vault::{unchecked, PluginConfig, Task}};
+
+use super::Context;
+
+
+/// Unchecked tasks of today and the next `days` daily notes, with the ones left over from the last `overdue_days` on top
+pub fn agenda(img: &mut DisplayImage, ctx: &Context, days: u32, overdue_days: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let today = ctx.now.date();
+    let config = ctx.vault.config()?;
+    let mut lines: Vec<(String, Color)> = Vec::new();
+
+    let mut overdue = Vec::new();
+    for back in (1..=overdue_days).rev() {
+        let date = today - Duration::days(back.into());
+        for task in day_tasks(ctx, &config, date)? {
+            overdue.push(format!("{} {} {}\n", task.status.glyph(), date.format("%a %-d"), task_text(&task)));
+        }
+    }
+
+    if !overdue.is_empty() {
+        lines.push(("Overdue\n".to_string(), render::TEXT_ACCENT));
+        lines.extend(overdue.into_iter().map(|line| (line, render::TEXT_ACCENT)));
+    }
+
+    for ahead in 0..=days {
+        let date = today + Duration::days(ahead.into());
+        let tasks = day_tasks(ctx, &config, date)?;
+        if tasks.is_empty() {
+            continue;
+        }
+
+        lines.push((format!("{}\n", day_name(date, today)), render::TEXT));
+        for task in tasks {
+            // Today's tasks that should have been done by now stand out
+            let late = date == today && task.ends(date).is_some_and(|end| end < ctx.now);
+            lines.push((format!("{} {}\n", task.status.glyph(), task_text(&task)), if late { render::TEXT_ACCENT } else { render::TEXT }));
+        }
+    }
+
+    if lines.is_empty() {
+        lines.push(("Nothing planned".to_string(), render::TEXT));
+    }
+
+    let spans: Vec<_> = lines.iter().map(|(text, color)| (text.as_str(), *color)).collect();
+
+    let rect = Rect::at(0, 0).of_size(img.width(), img.height());
+    render::draw_rich_text(img, rect, &spans)
+}
+
+
+/// Unchecked tasks of the day, timed ones first by their time; none when there is no note
+fn day_tasks(ctx: &Context, config: &PluginConfig, date: NaiveDate) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+    let Some(note) = ctx.vault.note_in(config, date)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut tasks = unchecked(note.tasks);
+    tasks.sort_by_key(|task| (task.time.is_none(), task.time.map(|(t, _)| t)));
+    Ok(tasks)
+}
+
+
+fn task_text(task: &Task) -> String {
+    match task.time {
+        Some((t, _)) => format!("{} {}", t.format("%H:%M"), task.text.trim()),
+        None => task.text.trim().to_string(),
+    }
+}
+
+
+/// `Today`, `Tomorrow`, or the weekday and day of the month like `Mon 20`
+fn day_name(date: NaiveDate, today: NaiveDate) -> String {
+    match (date - today).num_days() {
+        0 => "Today".to_string(),
+        1 => "Tomorrow".to_string(),
+        _ => date.format("%a %-d").to_string(),
+    }
+}
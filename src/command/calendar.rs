@@ -1,24 +1,229 @@
-use log::warn;
+use std::path::PathBuf;
 
-use crate::{driver::DisplayImage, vault::{collect_tasks, note_to_ast, read_today_note}};
+use chrono::{Duration, NaiveDateTime, NaiveTime, Timelike};
+use cosmic_text::Color;
+use image::Rgb;
+use imageproc::rect::Rect;
+use log::{debug, warn};
 
+use crate::{driver::{DisplayImage, ACCENT, BLACK}, ics::Calendar, render, vault::{collect_tasks, collect_timed, note_to_ast}};
 
-pub fn calendar(img: &mut DisplayImage) -> Result<(), Box<dyn std::error::Error>> {
-    let now = chrono::Local::now();
+use super::Context;
 
-    match read_today_note() {
+
+const DATE_PRETTY: &str = "%A, %d %B";
+
+// Space taken by the date at the top
+const HEADER_HEIGHT: u32 = 20;
+// Space taken by the hour labels on the left
+const LABELS_WIDTH: u32 = 48;
+// Hours are never squeezed tighter than this so that a line of text fits
+const HOUR_MIN_HEIGHT: u32 = 24;
+
+// Width of the bar marking events
+const EVENT_BAR: u32 = 4;
+
+const GRID: Rgb<u8> = Rgb([0xA0, 0xA0, 0xA0]);
+
+
+pub fn calendar(img: &mut DisplayImage, ctx: &Context, ics: &[PathBuf]) -> Result<(), Box<dyn std::error::Error>> {
+    let now = ctx.now;
+
+    let events = if ics.is_empty() {
+        Vec::new()
+    } else {
+        match Calendar::load(ics) {
+            Ok(calendar) => calendar.events_on(now.date()),
+            Err(e) => {
+                warn!("Could not read the calendars: {}", e);
+                Vec::new()
+            },
+        }
+    };
+
+    debug!("Found events:\n{:#?}", events);
+
+    // All-day events go next to the date
+
+    let mut header = now.format(DATE_PRETTY).to_string();
+    for event in events.iter().filter(|event| event.all_day) {
+        header.push_str(&format!(" · {}", event.summary.trim()));
+    }
+
+    let rect = Rect::at(0, 0).of_size(img.width(), HEADER_HEIGHT);
+    render::draw_text(img, Color::rgb(0, 0, 0), rect, &header)?;
+
+    // Fit as many hours as the panel allows, starting with the one before the current
+    let hours = ((img.height() - HEADER_HEIGHT) / HOUR_MIN_HEIGHT).clamp(1, 24);
+    let timeline = Timeline {
+        top: HEADER_HEIGHT as i32,
+        hour_height: (img.height() - HEADER_HEIGHT) as f32 / hours as f32,
+        start: now.hour().saturating_sub(1).min(24 - hours),
+        hours,
+    };
+
+    for h in timeline.start..(timeline.start + timeline.hours) {
+        let y = timeline.y(NaiveTime::from_hms_opt(h, 0, 0).unwrap());
+        render::draw_line(img, GRID, (LABELS_WIDTH as i32, y), (img.width() as i32 - 1, y));
+
+        let rect = Rect::at(0, y).of_size(LABELS_WIDTH, HOUR_MIN_HEIGHT);
+        render::draw_text(img, Color::rgb(0, 0, 0), rect, &format!("{:02}:00", h))?;
+    }
+
+    // Timed tasks and events on the same timeline
+
+    let day = now.date().and_hms_opt(0, 0, 0).unwrap();
+    let mut items = Vec::new();
+
+    match ctx.vault.read_note(now.date()) {
         Err(e) => {
             warn!("Could not open today note: {}", e);
         },
         Ok(note) => {
             let ast = note_to_ast(&note);
-            let tasks = collect_tasks(&ast, &note, true)?;
+            let tasks = collect_tasks(&ast, &note)?;
+
+            let mut timed = Vec::new();
+            collect_timed(&tasks, &mut timed);
+
+            debug!("Found timed tasks:\n{:#?}", timed);
+
+            for task in timed {
+                let (Some((start, _)), Some(end)) = (task.time, task.ends(now.date())) else {
+                    continue;
+                };
 
-            for task in tasks {
+                let text = if task.status.is_open() {
+                    task.text.trim().to_string()
+                } else {
+                    format!("{} {}", task.status.glyph(), task.text.trim())
+                };
 
+                items.push(Item { text, start, end: end_today(end, day), event: false });
             }
         },
     }
 
+    for event in events.iter().filter(|event| !event.all_day) {
+        // Cut to today
+        let start = event.start.max(day).time();
+
+        items.push(Item { text: event.summary.trim().to_string(), start, end: end_today(event.end, day), event: true });
+    }
+
+    // Where the items are, without the ones over before the visible part of the day
+    let visible = NaiveTime::from_hms_opt(timeline.start, 0, 0).unwrap();
+    let mut spans: Vec<_> = items.iter()
+        .filter(|item| item.start >= visible || item.end > visible)
+        .map(|item| {
+            let y_start = timeline.y(item.start);
+            let y_end = timeline.y(item.end).max(y_start + HOUR_MIN_HEIGHT as i32 / 2);
+            (item, y_start, y_end)
+        })
+        .collect();
+    spans.sort_by_key(|(_, y_start, _)| *y_start);
+
+    let columns = columns(&spans.iter().map(|(_, y_start, y_end)| (*y_start, *y_end)).collect::<Vec<_>>());
+    let width = img.width() - LABELS_WIDTH - 4;
+
+    for ((item, y_start, y_end), (column, count)) in spans.into_iter().zip(columns) {
+        // Side by side when they overlap
+        let column_width = width / count as u32;
+        let left = LABELS_WIDTH as i32 + 2 + (column as u32 * column_width) as i32;
+        let gap = if count > 1 { 2 } else { 0 };
+
+        let rect = Rect::at(left, y_start).of_size(column_width - gap, (y_end - y_start) as u32);
+
+        // Events stand out with a bar on the left, tasks are plain boxes
+        let inset = if item.event {
+            render::draw_box(img, ACCENT, rect);
+            render::fill_box(img, BLACK, Rect::at(rect.left(), rect.top()).of_size(EVENT_BAR, rect.height()));
+            EVENT_BAR as i32 + 2
+        } else {
+            render::draw_box(img, BLACK, rect);
+            2
+        };
+
+        let rect = Rect::at(rect.left() + inset, rect.top()).of_size(rect.width().saturating_sub(inset as u32 + 2).max(1), rect.height());
+        render::draw_text(img, Color::rgb(0, 0, 0), rect, &item.text)?;
+    }
+
+    let y = timeline.y(now.time());
+    render::draw_line(img, ACCENT, (LABELS_WIDTH as i32 - 4, y), (img.width() as i32 - 1, y));
+    render::draw_line(img, ACCENT, (LABELS_WIDTH as i32 - 4, y + 1), (img.width() as i32 - 1, y + 1));
+
     Ok(())
 }
+
+
+/// Time of `end` on the `day`, the last second of it when `end` is on a later day
+fn end_today(end: NaiveDateTime, day: NaiveDateTime) -> NaiveTime {
+    if end >= day + Duration::days(1) {
+        NaiveTime::from_hms_opt(23, 59, 59).unwrap()
+    } else {
+        end.time()
+    }
+}
+
+
+/// A task or an event on the timeline
+struct Item {
+    text: String,
+    start: NaiveTime,
+    end: NaiveTime,
+    event: bool,
+}
+
+
+/// Column of each span and how many columns its group of overlapping spans takes, spans sorted by start
+fn columns(spans: &[(i32, i32)]) -> Vec<(usize, usize)> {
+    let mut columns = vec![(0, 1); spans.len()];
+
+    // Overlapping spans and where each of their columns ends
+    let mut group: Vec<usize> = Vec::new();
+    let mut ends: Vec<i32> = Vec::new();
+
+    for (i, (start, end)) in spans.iter().enumerate() {
+        if ends.iter().all(|e| e <= start) {
+            for j in group.drain(..) {
+                columns[j].1 = ends.len();
+            }
+            ends.clear();
+        }
+
+        let column = match ends.iter().position(|e| e <= start) {
+            Some(column) => column,
+            None => {
+                ends.push(*end);
+                ends.len() - 1
+            },
+        };
+        ends[column] = *end;
+
+        columns[i].0 = column;
+        group.push(i);
+    }
+
+    for j in group {
+        columns[j].1 = ends.len();
+    }
+
+    columns
+}
+
+
+/// Vertical time axis of the calendar
+struct Timeline {
+    top: i32,
+    hour_height: f32,
+    start: u32,
+    hours: u32,
+}
+
+impl Timeline {
+    fn y(&self, time: NaiveTime) -> i32 {
+        let minutes = time.hour() as f32 * 60.0 + time.minute() as f32 - self.start as f32 * 60.0;
+        let minutes = minutes.clamp(0.0, self.hours as f32 * 60.0);
+        self.top + (minutes * self.hour_height / 60.0) as i32
+    }
+}
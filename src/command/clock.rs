@@ -0,0 +1,98 @@
+use chrono::{Datelike, Timelike};
+use imageproc::rect::Rect;
+use log::{debug, warn};
+
+use crate::{driver::{DisplayImage, BLACK}, render, vault::{collect_tasks, collect_timed, note_to_ast, unchecked}};
+
+use super::Context;
+
+
+const DATE_PRETTY: &str = "%A, %d %B";
+const DIGITS_PRETTY: &str = "%H:%M";
+
+const MARGIN: i32 = 6;
+// Space taken by the date at the top
+const HEADER_HEIGHT: u32 = 20;
+const PROGRESS_HEIGHT: u32 = 10;
+const LINE_HEIGHT: u32 = 18;
+
+
+/// Size of the digits in pixels, as big as the panel allows
+fn digits_size(height: u32) -> f32 {
+    height as f32 * 0.35
+}
+
+
+/// Where the digits are on a view of `width` by `height`, the only part that changes every minute
+pub fn digits_rect(width: u32, height: u32) -> Rect {
+    let digits = (digits_size(height) * 1.25) as u32;
+    Rect::at(0, HEADER_HEIGHT as i32 + MARGIN).of_size(width, digits)
+}
+
+
+pub fn clock(img: &mut DisplayImage, ctx: &Context) -> Result<(), Box<dyn std::error::Error>> {
+    let now = ctx.now;
+    let width = img.width() - 2 * MARGIN as u32;
+
+    // Date on the left, week on the right
+
+    let rect = Rect::at(MARGIN, MARGIN).of_size(width, HEADER_HEIGHT);
+    render::draw_text(img, render::TEXT, rect, &now.format(DATE_PRETTY).to_string())?;
+
+    let week = format!("W{:02}", now.iso_week().week());
+    let week_width = render::text_width(&week, 16.0)?;
+    let rect = Rect::at(img.width() as i32 - MARGIN - week_width as i32, MARGIN).of_size(week_width, HEADER_HEIGHT);
+    render::draw_text(img, render::TEXT, rect, &week)?;
+
+    // Digits
+
+    let rect = digits_rect(img.width(), img.height());
+    render::draw_text_centered(img, render::TEXT, rect, &now.format(DIGITS_PRETTY).to_string(), digits_size(img.height()))?;
+
+    // How much of the day is gone
+
+    let top = rect.bottom() + MARGIN;
+    let rect = Rect::at(MARGIN, top).of_size(width, PROGRESS_HEIGHT);
+    render::draw_box(img, BLACK, rect);
+
+    let done = (width - 2) * now.num_seconds_from_midnight() / 86_400;
+    if done > 0 {
+        render::fill_box(img, BLACK, Rect::at(MARGIN + 1, top + 1).of_size(done, PROGRESS_HEIGHT - 2));
+    }
+
+    // What's next
+
+    if let Some(next) = next_task(ctx)? {
+        let rect = Rect::at(MARGIN, rect.bottom() + MARGIN).of_size(width, LINE_HEIGHT);
+        render::draw_text(img, render::TEXT, rect, &next)?;
+    }
+
+    Ok(())
+}
+
+
+/// The first unchecked timed task that is not over yet
+fn next_task(ctx: &Context) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let note = match ctx.vault.read_note(ctx.now.date()) {
+        Ok(note) => note,
+        Err(e) => {
+            warn!("Could not open today note: {}", e);
+            return Ok(None);
+        },
+    };
+
+    let ast = note_to_ast(&note);
+    let tasks = unchecked(collect_tasks(&ast, &note)?);
+
+    let mut timed = Vec::new();
+    collect_timed(&tasks, &mut timed);
+
+    let next = timed.into_iter()
+        .filter(|task| task.ends(ctx.now.date()).is_some_and(|end| end > ctx.now))
+        .filter_map(|task| task.time.map(|time| (time, task)))
+        .min_by_key(|((start, _), _)| *start);
+
+    debug!("Next task: {:?}", next);
+
+    Ok(next.map(|((start, _), task)| format!("{} {}", start.format(DIGITS_PRETTY), task.text.trim())))
+}
@@ -1,10 +1,21 @@
 use cosmic_text::Color;
+use image::Rgb;
 use imageproc::rect::Rect;
 
-use crate::{driver::{DisplayImage, BLACK}, render};
+use crate::{args::Pattern, driver::{DisplayImage, BLACK, WHITE}, render};
 
 
-pub fn debug(img: &mut DisplayImage) -> Result<(), Box<dyn std::error::Error>> {
+pub fn debug(img: &mut DisplayImage, pattern: Pattern) -> Result<(), Box<dyn std::error::Error>> {
+    match pattern {
+        Pattern::Axes => axes(img),
+        Pattern::Greys => greys(img),
+        Pattern::Gradient => gradient(img),
+        Pattern::Checker => checker(img),
+    }
+}
+
+
+fn axes(img: &mut DisplayImage) -> Result<(), Box<dyn std::error::Error>> {
     let rect = Rect::at(0, 0).of_size(img.width(), img.height());
     render::draw_box(img, BLACK, rect);
     let rect = Rect::at(1, 1).of_size(img.width() - 2, img.height() - 2);
@@ -16,11 +27,66 @@ pub fn debug(img: &mut DisplayImage) -> Result<(), Box<dyn std::error::Error>> {
     render::draw_text(img, Color::rgb(0, 0, 0), rect, text)?;
 
 
-    let rect = Rect::at(50, 50).of_size(100, 100);
+    // A crossed square in the middle, sized after the panel
+    let side = img.width().min(img.height()) as i32 / 2;
+    let (x, y) = ((img.width() as i32 - side) / 2, (img.height() as i32 - side) / 2);
+
+    let rect = Rect::at(x, y).of_size(side as u32, side as u32);
     render::draw_box(img, BLACK, rect);
 
-    render::draw_line(img, BLACK, (55, 55), (145, 145));
-    render::draw_line(img, BLACK, (135, 55), (65, 145));
+    let pad = side / 20;
+    render::draw_line(img, BLACK, (x + pad, y + pad), (x + side - pad, y + side - pad));
+    render::draw_line(img, BLACK, (x + side - pad, y + pad), (x + pad, y + side - pad));
+
+    Ok(())
+}
+
+
+fn greys(img: &mut DisplayImage) -> Result<(), Box<dyn std::error::Error>> {
+    // Middles of the ranges that `ColorGreyscale` maps to each level
+    let levels = [("Black", 0x20), ("Dark", 0x60), ("Light", 0xA0), ("White", 0xE0)];
+
+    let band = img.width() / levels.len() as u32;
+
+    for (i, (name, level)) in levels.into_iter().enumerate() {
+        let rect = Rect::at(i as i32 * band as i32, 0).of_size(band, img.height());
+        render::fill_box(img, Rgb([level, level, level]), rect);
+
+        let text = if level < 0x80 { Color::rgb(0xFF, 0xFF, 0xFF) } else { Color::rgb(0, 0, 0) };
+        let rect = Rect::at(rect.left() + 2, 2).of_size(band - 4, img.height() - 4);
+        render::draw_text(img, text, rect, &format!("{}\n0x{:02X}", name, level))?;
+    }
+
+    Ok(())
+}
+
+
+fn gradient(img: &mut DisplayImage) -> Result<(), Box<dyn std::error::Error>> {
+    let width = img.width();
+
+    for (x, _y, pixel) in img.enumerate_pixels_mut() {
+        let level = (x * u8::MAX as u32 / (width - 1)) as u8;
+        *pixel = Rgb([level, level, level]);
+    }
+
+    Ok(())
+}
+
+
+fn checker(img: &mut DisplayImage) -> Result<(), Box<dyn std::error::Error>> {
+    const CELL: u32 = 8;
+
+    let (half_w, half_h) = (img.width() / 2, img.height() / 2);
+
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        *pixel = if x < half_w {
+            if (x / CELL + y / CELL).is_multiple_of(2) { BLACK } else { WHITE }
+        } else if y < half_h {
+            BLACK
+        } else {
+            WHITE
+        };
+    }
 
     Ok(())
 }
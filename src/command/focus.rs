@@ -0,0 +1,266 @@
+use chrono::{Duration, NaiveDateTime};
+use imageproc::rect::Rect;
+use log::{debug, warn};
+
+use crate::{driver::{DisplayImage, BLACK}, render, vault::{collect_tasks, note_to_ast, unchecked, Task}};
+
+use super::Context;
+
+
+const TIME_PRETTY: &str = "%H:%M";
+
+const MARGIN: i32 = 6;
+// Space taken by the task at the top
+const HEADER_HEIGHT: u32 = 20;
+const PROGRESS_HEIGHT: u32 = 10;
+const LINE_HEIGHT: u32 = 18;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Work,
+    Break,
+}
+
+
+/// A work session that ran to the end
+#[derive(Debug)]
+pub struct Session {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub task: Option<String>,
+}
+
+impl Session {
+    /// Line for the daily note: `- 10:25–10:50 🍅 Write report`
+    pub fn log_line(&self) -> String {
+        format!(
+            "- {}–{} 🍅 {}",
+            self.start.format(TIME_PRETTY),
+            self.end.format(TIME_PRETTY),
+            self.task.as_deref().unwrap_or("Focus"),
+        )
+    }
+}
+
+
+/// Work and break cycle on one of today's unchecked tasks
+#[derive(Clone, Debug)]
+pub struct Focus {
+    /// Unchecked tasks of today to pick from
+    pub tasks: Vec<String>,
+    pub selected: usize,
+    pub phase: Phase,
+    work: Duration,
+    rest: Duration,
+    /// Time left in the phase when it was last started or paused
+    left: Duration,
+    /// When the phase was last started or resumed, `None` when not running
+    since: Option<NaiveDateTime>,
+    /// When the work session was started, for the log
+    began: Option<NaiveDateTime>,
+}
+
+impl Focus {
+    pub fn new(tasks: Vec<String>, work: Duration, rest: Duration) -> Self {
+        Self {
+            tasks,
+            selected: 0,
+            phase: Phase::Work,
+            work,
+            rest,
+            left: work,
+            since: None,
+            began: None,
+        }
+    }
+
+    pub fn task(&self) -> Option<&str> {
+        self.tasks.get(self.selected).map(String::as_str)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.since.is_some()
+    }
+
+    /// How long the current phase is in total
+    pub fn length(&self) -> Duration {
+        match self.phase {
+            Phase::Work => self.work,
+            Phase::Break => self.rest,
+        }
+    }
+
+    pub fn remaining(&self, now: NaiveDateTime) -> Duration {
+        match self.since {
+            Some(since) => (self.left - (now - since)).max(Duration::zero()),
+            None => self.left,
+        }
+    }
+
+    /// Start or resume the phase, or pause it if it's running
+    pub fn toggle(&mut self, now: NaiveDateTime) {
+        if self.is_running() {
+            self.left = self.remaining(now);
+            self.since = None;
+        } else {
+            self.since = Some(now);
+            if self.phase == Phase::Work {
+                self.began.get_or_insert(now);
+            }
+        }
+    }
+
+    /// Abandon the cycle, the next start is a new work session
+    pub fn stop(&mut self) {
+        self.phase = Phase::Work;
+        self.left = self.work;
+        self.since = None;
+        self.began = None;
+    }
+
+    /// Pick the next task, back to the first after the last
+    pub fn next_task(&mut self) {
+        if !self.tasks.is_empty() {
+            self.selected = (self.selected + 1) % self.tasks.len();
+        }
+    }
+
+    /// Move to the next phase when the time is up, returning the work session if it's the one that ended.
+    ///
+    /// Breaks start on their own, the next work session waits for a start.
+    pub fn advance(&mut self, now: NaiveDateTime) -> Option<Session> {
+        let since = self.since?;
+        if self.remaining(now) > Duration::zero() {
+            return None;
+        }
+
+        let end = since + self.left;
+
+        match self.phase {
+            Phase::Work => {
+                let session = Session {
+                    start: self.began.take().unwrap_or(end - self.work),
+                    end,
+                    task: self.task().map(str::to_string),
+                };
+
+                self.phase = Phase::Break;
+                self.left = self.rest;
+                self.since = Some(end);
+
+                Some(session)
+            },
+            Phase::Break => {
+                self.stop();
+                None
+            },
+        }
+    }
+
+    /// Time until the minutes left change, the whole wait when not running
+    pub fn until_tick(&self, now: NaiveDateTime) -> std::time::Duration {
+        if !self.is_running() {
+            return std::time::Duration::from_secs(3600);
+        }
+
+        let left = self.remaining(now).num_milliseconds() as u64;
+        let tick = match left % 60_000 {
+            0 => left.min(60_000),
+            ms => ms,
+        };
+
+        std::time::Duration::from_millis(tick)
+    }
+}
+
+
+/// Size of the digits in pixels, as big as the panel allows
+fn digits_size(height: u32) -> f32 {
+    height as f32 * 0.3
+}
+
+
+/// Where the time left is on a view of `width` by `height`, the only part that changes every minute
+pub fn timer_rect(width: u32, height: u32) -> Rect {
+    let timer = (digits_size(height) * 1.25) as u32 + MARGIN as u32 + PROGRESS_HEIGHT;
+    Rect::at(0, HEADER_HEIGHT as i32 + MARGIN).of_size(width, timer)
+}
+
+
+pub fn focus(img: &mut DisplayImage, ctx: &Context, state: &Focus) -> Result<(), Box<dyn std::error::Error>> {
+    let width = img.width() - 2 * MARGIN as u32;
+
+    // What it's all about
+
+    let title = match state.phase {
+        Phase::Work => state.task().unwrap_or("No open tasks today"),
+        Phase::Break => "Break",
+    };
+
+    let rect = Rect::at(MARGIN, MARGIN).of_size(width, HEADER_HEIGHT);
+    render::draw_text(img, render::TEXT, rect, title)?;
+
+    // Minutes left, counting the one that has started
+
+    let timer = timer_rect(img.width(), img.height());
+    let left = state.remaining(ctx.now);
+    let minutes = (left.num_seconds() + 59) / 60;
+
+    let rect = Rect::at(0, timer.top()).of_size(img.width(), (digits_size(img.height()) * 1.25) as u32);
+    render::draw_text_centered(img, render::TEXT, rect, &format!("{} min", minutes), digits_size(img.height()))?;
+
+    // How much of the phase is gone
+
+    let top = timer.bottom() + 1 - PROGRESS_HEIGHT as i32;
+    render::draw_box(img, BLACK, Rect::at(MARGIN, top).of_size(width, PROGRESS_HEIGHT));
+
+    let total = state.length().num_seconds().max(1);
+    let done = ((width - 2) as i64 * (total - left.num_seconds()).clamp(0, total) / total) as u32;
+    if done > 0 {
+        render::fill_box(img, BLACK, Rect::at(MARGIN + 1, top + 1).of_size(done, PROGRESS_HEIGHT - 2));
+    }
+
+    // What the keys do
+
+    let start = match (state.is_running(), left < state.length()) {
+        (true, _) => "pause",
+        (false, true) => "resume",
+        (false, false) => "start",
+    };
+
+    let rect = Rect::at(MARGIN, timer.bottom() + MARGIN).of_size(width, LINE_HEIGHT);
+    render::draw_text(img, render::TEXT, rect, &format!("1 {} · 2 stop · 3 next task", start))?;
+
+    Ok(())
+}
+
+
+/// Unchecked tasks of today, subtasks after their parents
+pub fn open_tasks(ctx: &Context) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let note = match ctx.vault.read_note(ctx.now.date()) {
+        Ok(note) => note,
+        Err(e) => {
+            warn!("Could not open today note: {}", e);
+            return Ok(Vec::new());
+        },
+    };
+
+    let ast = note_to_ast(&note);
+    let tasks = unchecked(collect_tasks(&ast, &note)?);
+
+    let mut texts = Vec::new();
+    flatten(&tasks, &mut texts);
+
+    debug!("Tasks to focus on: {:?}", texts);
+
+    Ok(texts)
+}
+
+
+fn flatten(tasks: &[Task], texts: &mut Vec<String>) {
+    for task in tasks {
+        texts.push(task.text.trim().to_string());
+        flatten(&task.subtasks, texts);
+    }
+}
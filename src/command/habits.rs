@@ -0,0 +1,83 @@
+use chrono::{Datelike, Duration};
+use image::Rgb;
+use imageproc::rect::Rect;
+use log::debug;
+
+use crate::{app_error::AppError, driver::{DisplayImage, ACCENT, BLACK}, render};
+
+use super::Context;
+
+
+// Space taken by the habit names on the left
+const LABELS_WIDTH: u32 = 96;
+const MARGIN: i32 = 4;
+const LINE_HEIGHT: u32 = 18;
+// Cells are never bigger than this, however few days there are
+const CELL_MAX: u32 = 14;
+
+/// Fill of done cells by how much was done compared to the best day
+const SHADES: [Rgb<u8>; 3] = [Rgb([0xAA, 0xAA, 0xAA]), Rgb([0x55, 0x55, 0x55]), BLACK];
+
+
+/// A GitHub-style grid per habit: a column per week, a row per weekday, today highlighted
+pub fn habits(img: &mut DisplayImage, ctx: &Context, names: &[String], days: u32) -> Result<(), Box<dyn std::error::Error>> {
+    if names.is_empty() {
+        return Err(AppError::Data("No habits to track, set HABITS".to_string()).into());
+    }
+
+    let days = days.max(1);
+    let today = ctx.now.date();
+    let habits = ctx.vault.habits(names, today, days)?;
+
+    debug!("Habits:\n{:#?}", habits);
+
+    // The first column starts on the Monday of the first day's week
+    let first = today - Duration::days(days as i64 - 1);
+    let offset = first.weekday().num_days_from_monday();
+    let weeks = (offset + days).div_ceil(7);
+
+    let band = img.height() / habits.len() as u32;
+    let cell = (band / 7)
+        .min((img.width() - LABELS_WIDTH - MARGIN as u32) / weeks)
+        .clamp(2, CELL_MAX);
+
+    for (i, habit) in habits.iter().enumerate() {
+        let top = (i as u32 * band) as i32;
+
+        let rect = Rect::at(MARGIN, top).of_size(LABELS_WIDTH - MARGIN as u32, LINE_HEIGHT);
+        render::draw_text(img, render::TEXT, rect, &habit.name)?;
+
+        if band >= 2 * LINE_HEIGHT {
+            let rect = Rect::at(MARGIN, top + LINE_HEIGHT as i32).of_size(LABELS_WIDTH - MARGIN as u32, LINE_HEIGHT);
+            render::draw_text(img, render::TEXT, rect, &format!("{} in a row", habit.streak()))?;
+        }
+
+        // Grids are centered in their bands
+        let grid_top = top + band.saturating_sub(7 * cell) as i32 / 2;
+        let best = habit.values.iter().copied().fold(1.0, f32::max);
+
+        for (day, value) in habit.values.iter().enumerate() {
+            let index = offset + day as u32;
+            let x = (LABELS_WIDTH + index / 7 * cell) as i32;
+            let y = grid_top + (index % 7 * cell) as i32;
+            let rect = Rect::at(x, y).of_size(cell - 1, cell - 1);
+
+            if *value > 0.0 {
+                let shade = ((value / best * SHADES.len() as f32).ceil() as usize).clamp(1, SHADES.len()) - 1;
+                render::fill_box(img, SHADES[shade], rect);
+                render::draw_box(img, BLACK, rect);
+            } else {
+                // A dot, so that the grid is still visible
+                let dot = (cell / 4).max(1);
+                let at = (cell - 1 - dot) as i32 / 2;
+                render::fill_box(img, BLACK, Rect::at(x + at, y + at).of_size(dot, dot));
+            }
+
+            if day as u32 == days - 1 {
+                render::draw_box(img, ACCENT, Rect::at(x - 1, y - 1).of_size(cell + 1, cell + 1));
+            }
+        }
+    }
+
+    Ok(())
+}
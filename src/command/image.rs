@@ -0,0 +1,71 @@
+use std::path::Path;
+
+use image::{imageops::{self, FilterType}, DynamicImage};
+use log::info;
+
+use crate::{args::{Dither, Scaling}, driver::DisplayImage, render::{self, Picture}};
+
+
+/// How a picture is fitted to the panel and turned into its inks
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    pub fit: Scaling,
+    pub gamma: f32,
+    pub contrast: f32,
+    /// Shades the display mode can show, see `DisplayMode::levels`
+    pub levels: u8,
+    pub dither: Dither,
+}
+
+
+pub fn image(img: &mut DisplayImage, path: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    info!("Loading picture from {}", path.display());
+
+    // GIFs are decoded up to the first frame
+    picture(img, image::open(path)?, options)
+}
+
+
+pub fn picture(img: &mut DisplayImage, picture: DynamicImage, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let picture: Picture = picture.to_luma32f();
+
+    // Turn the panel to match the picture, the driver accepts both orientations
+    if (picture.width() > picture.height()) != (img.width() > img.height()) {
+        *img = DisplayImage::from_pixel(img.height(), img.width(), *img.get_pixel(0, 0));
+    }
+
+    let picture = prepare(picture, (img.width(), img.height()), options.fit, options.gamma, options.contrast);
+
+    // Center, cropping whatever doesn't fit
+    let x = (img.width() as i32 - picture.width() as i32) / 2;
+    let y = (img.height() as i32 - picture.height() as i32) / 2;
+
+    render::draw_picture(img, &picture, (x, y), options.levels, options.dither);
+
+    Ok(())
+}
+
+
+/// Scale the picture for a space of `size` and apply the tone adjustments
+pub fn prepare(mut picture: Picture, size: (u32, u32), fit: Scaling, gamma: f32, contrast: f32) -> Picture {
+    let (w, h) = (picture.width() as f32, picture.height() as f32);
+    let (img_w, img_h) = (size.0 as f32, size.1 as f32);
+
+    let scale = match fit {
+        Scaling::Fit => (img_w / w).min(img_h / h),
+        Scaling::Fill => (img_w / w).max(img_h / h),
+        Scaling::Center => 1.0,
+    };
+
+    if scale != 1.0 {
+        let (w, h) = (((w * scale).round() as u32).max(1), ((h * scale).round() as u32).max(1));
+        picture = imageops::resize(&picture, w, h, FilterType::Lanczos3);
+    }
+
+    for pixel in picture.pixels_mut() {
+        let value = (pixel.0[0] - 0.5) * contrast + 0.5;
+        pixel.0[0] = value.clamp(0.0, 1.0).powf(1.0 / gamma);
+    }
+
+    picture
+}
@@ -0,0 +1,66 @@
+use chrono::NaiveDateTime;
+use cosmic_text::Color;
+use imageproc::rect::Rect;
+
+use crate::{app_error::AppError, driver::{DisplayImage, ACCENT, BLACK}, render};
+
+
+const MARGIN: u32 = 6;
+// Space taken by the title of an error
+const TITLE_HEIGHT: u32 = 22;
+const BADGE_SIZE: f32 = 12.0;
+
+
+pub fn message(img: &mut DisplayImage, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let rect = Rect::at(1, 1).of_size(img.width() - 2, img.height() - 2);
+    render::draw_box(img, BLACK, rect);
+
+    let rect = Rect::at(MARGIN as i32, MARGIN as i32).of_size(img.width() - 2 * MARGIN, img.height() - 2 * MARGIN);
+    render::draw_text(img, Color::rgb(0, 0, 0), rect, text)?;
+
+    Ok(())
+}
+
+
+/// Something went wrong: the title on an accent bar, the details below in small print
+pub fn error(img: &mut DisplayImage, title: &str, details: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let rect = Rect::at(0, 0).of_size(img.width(), img.height());
+    render::draw_box(img, ACCENT, rect);
+    render::draw_box(img, ACCENT, Rect::at(1, 1).of_size(img.width() - 2, img.height() - 2));
+
+    render::fill_box(img, ACCENT, Rect::at(0, 0).of_size(img.width(), TITLE_HEIGHT));
+    let rect = Rect::at(MARGIN as i32, 2).of_size(img.width() - 2 * MARGIN, TITLE_HEIGHT - 2);
+    render::draw_text(img, render::TEXT_INVERTED, rect, title)?;
+
+    let top = TITLE_HEIGHT + MARGIN / 2;
+    let rect = Rect::at(MARGIN as i32, top as i32).of_size(img.width() - 2 * MARGIN, img.height() - top - MARGIN);
+    render::draw_rich_text_sized(img, rect, &[(details, render::TEXT)], 12.0)?;
+
+    Ok(())
+}
+
+
+/// A view could not be drawn: what kind of problem it is, the error, and when it happened
+pub fn failure(img: &mut DisplayImage, e: &(dyn std::error::Error + 'static), now: NaiveDateTime) -> Result<(), Box<dyn std::error::Error>> {
+    let title = e.downcast_ref::<AppError>().map_or("Error", AppError::category);
+    error(img, title, &e.to_string())?;
+
+    let time = now.format("%H:%M").to_string();
+    let width = render::text_width(&time, 16.0)?;
+    let rect = Rect::at((img.width() - width - MARGIN) as i32, 2).of_size(width + 1, TITLE_HEIGHT - 2);
+    render::draw_text(img, render::TEXT_INVERTED, rect, &time)
+}
+
+
+/// Mark content that is out of date because drawing it again failed at `now`
+pub fn badge(img: &mut DisplayImage, now: NaiveDateTime) -> Result<(), Box<dyn std::error::Error>> {
+    let text = format!("! {}", now.format("%H:%M"));
+    let width = render::text_width(&text, BADGE_SIZE)? + 4;
+    let height = (BADGE_SIZE * 1.125).ceil() as u32 + 2;
+
+    let rect = Rect::at((img.width() - width) as i32, 0).of_size(width, height);
+    render::fill_box(img, ACCENT, rect);
+
+    let rect = Rect::at(rect.left() + 2, 1).of_size(width - 2, height - 1);
+    render::draw_rich_text_sized(img, rect, &[(&text, render::TEXT_INVERTED)], BADGE_SIZE)
+}
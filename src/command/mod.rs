@@ -1,4 +1,53 @@
+use chrono::NaiveDateTime;
+
+use crate::{app_error::AppError, vault::Vault};
+
+pub mod agenda;
 pub mod calendar;
+pub mod clock;
 pub mod debug;
+pub mod focus;
+pub mod habits;
+pub mod image;
+pub mod message;
+pub mod month;
 pub mod network;
+pub mod screen;
+pub mod script;
+pub mod section;
+pub mod status;
+pub mod sync;
 pub mod tasks;
+pub mod widgets;
+
+#[cfg(test)]
+mod tests;
+
+
+/// Everything the views take from the outside world
+pub struct Context {
+    /// Local time of the refresh
+    pub now: NaiveDateTime,
+    pub vault: Vault,
+    /// Network interface to describe
+    pub ifname: String,
+    /// Addresses of the network interface, one per line
+    pub net_info: fn(&str) -> Result<String, AppError>,
+    /// Wireless network of the interface
+    pub wifi_info: fn(&str) -> Result<Option<network::Wifi>, AppError>,
+    /// State of a systemd unit
+    pub unit_state: fn(&str) -> Result<String, AppError>,
+}
+
+impl Context {
+    pub fn new(ifname: &str) -> Self {
+        Self {
+            now: chrono::Local::now().naive_local(),
+            vault: Vault::from_env(),
+            ifname: ifname.to_string(),
+            net_info: network::net_info,
+            wifi_info: network::wifi_info,
+            unit_state: sync::unit_state,
+        }
+    }
+}
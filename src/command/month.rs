@@ -0,0 +1,100 @@
+use chrono::{Datelike, Duration, Weekday};
+use imageproc::rect::Rect;
+use log::{debug, warn};
+
+use crate::{driver::{DisplayImage, ACCENT, BLACK, WHITE}, render};
+
+use super::Context;
+
+
+const MARGIN: i32 = 2;
+const TITLE_HEIGHT: u32 = 20;
+const WEEKDAYS_HEIGHT: u32 = 14;
+const DAY_SIZE: f32 = 14.0;
+const COUNT_SIZE: f32 = 11.0;
+// Side of the square marking days with a note
+const NOTE_MARK: u32 = 4;
+
+/// Shown on days where every task is done
+const ALL_DONE: &str = "✓";
+
+
+/// The current month a week per row: days with a note are marked, with how many tasks are still open, today inverted
+pub fn month(img: &mut DisplayImage, ctx: &Context) -> Result<(), Box<dyn std::error::Error>> {
+    let today = ctx.now.date();
+    let first = today.with_day(1).unwrap();
+    let next = (first + Duration::days(31)).with_day(1).unwrap();
+    let days = (next - first).num_days() as u32;
+
+    let rect = Rect::at(MARGIN, 0).of_size(img.width() - 2 * MARGIN as u32, TITLE_HEIGHT);
+    render::draw_text_centered(img, render::TEXT, rect, &first.format("%B %Y").to_string(), 16.0)?;
+
+    // Monday first, as in the habits view
+    let offset = first.weekday().num_days_from_monday();
+    let weeks = (offset + days).div_ceil(7);
+
+    let cell_width = img.width() / 7;
+    let grid_top = (TITLE_HEIGHT + WEEKDAYS_HEIGHT) as i32;
+    let cell_height = (img.height() - grid_top as u32) / weeks;
+
+    let mut weekday = Weekday::Mon;
+    for column in 0..7 {
+        let rect = Rect::at((column * cell_width) as i32, TITLE_HEIGHT as i32).of_size(cell_width, WEEKDAYS_HEIGHT);
+        render::draw_text_centered(img, render::TEXT, rect, &weekday.to_string()[..2], COUNT_SIZE)?;
+        weekday = weekday.succ();
+    }
+
+    let config = ctx.vault.config()?;
+
+    for day in 0..days {
+        let date = first + Duration::days(day.into());
+        let index = offset + day;
+        let x = (index % 7 * cell_width) as i32;
+        let y = grid_top + (index / 7 * cell_height) as i32;
+        let cell = Rect::at(x, y).of_size(cell_width - 1, cell_height - 1);
+
+        let is_today = date == today;
+        let (text, fill) = if is_today { (render::TEXT_INVERTED, WHITE) } else { (render::TEXT, BLACK) };
+        if is_today {
+            render::fill_box(img, BLACK, cell);
+        }
+
+        let rect = Rect::at(x + MARGIN, y).of_size(cell.width() - MARGIN as u32, cell.height());
+        render::draw_rich_text_sized(img, rect, &[(&date.day().to_string(), text)], DAY_SIZE)?;
+
+        let counts = match ctx.vault.task_counts(&config, date) {
+            Ok(Some(counts)) => counts,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("Could not read the note of {}: {}", date, e);
+                continue;
+            },
+        };
+
+        debug!("{}: {:?}", date, counts);
+
+        let mark = Rect::at(x + MARGIN + 1, y + cell.height() as i32 - NOTE_MARK as i32 - MARGIN - 1).of_size(NOTE_MARK, NOTE_MARK);
+        render::fill_box(img, fill, mark);
+
+        // Tasks left open on past days stand out
+        let status = match counts.open() {
+            _ if counts.total == 0 => continue,
+            0 => (ALL_DONE.to_string(), text),
+            open if date < today => (open.to_string(), render::TEXT_ACCENT),
+            open => (open.to_string(), text),
+        };
+
+        let width = render::text_width(&status.0, COUNT_SIZE)?;
+        let at = (x + cell.width() as i32 - width as i32 - MARGIN, y + cell.height() as i32 - (COUNT_SIZE * 1.125) as i32);
+        let rect = Rect::at(at.0, at.1).of_size(width + 1, (COUNT_SIZE * 1.125).ceil() as u32);
+        render::draw_rich_text_sized(img, rect, &[(&status.0, status.1)], COUNT_SIZE)?;
+    }
+
+    // Lines between the weeks
+    for week in 1..weeks {
+        let y = grid_top + (week * cell_height) as i32 - 1;
+        render::draw_line(img, ACCENT, (0, y), (img.width() as i32 - 1, y));
+    }
+
+    Ok(())
+}
@@ -7,37 +7,111 @@ use regex::RegexBuilder;
 
 use crate::{app_error::AppError, driver::DisplayImage, render};
 
+use super::Context;
 
-fn net_info(interface: &str) -> Result<String, AppError> {
+
+pub fn net_info(interface: &str) -> Result<String, AppError> {
     info!("Acquiring network info");
     let re_inet = RegexBuilder::new(r#"^\s*(inet6?\s+\S+?)\s.*$"#)
         .multi_line(true)
         .build()?;
 
+    let failed = |message: String| AppError::Command { command: format!("ip addr show dev {}", interface), message };
+
     let output = Command::new("ip")
         .arg("addr")
         .arg("show")
         .arg("dev")
         .arg(interface)
-        .output()?;
+        .output()
+        .map_err(|e| failed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(failed(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
 
     let stdout = String::from_utf8(output.stdout)?;
 
     let mut output = String::new();
     for caps in re_inet.captures_iter(stdout.as_str()) {
         output.push_str(&caps[1]);
-        output.push_str("\n");
+        output.push('\n');
     }
 
     Ok(output)
 }
 
 
-pub fn network(img: &mut DisplayImage, ifname: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let text = net_info(ifname)?;
+/// Wireless network the interface is connected to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wifi {
+    pub ssid: String,
+    /// Signal strength in dBm
+    pub signal: i32,
+}
+
+impl Wifi {
+    /// Signal strength as bars, `▂▄▆█` for a strong one
+    pub fn bars(&self) -> String {
+        let bars = match self.signal {
+            s if s >= -55 => 4,
+            s if s >= -65 => 3,
+            s if s >= -75 => 2,
+            _ => 1,
+        };
+        "▂▄▆█".chars().take(bars).collect()
+    }
+}
+
+
+/// The network the interface is connected to, from `iw`; `None` when it's not connected
+pub fn wifi_info(interface: &str) -> Result<Option<Wifi>, AppError> {
+    let output = Command::new("iw")
+        .args(["dev", interface, "link"])
+        .output()
+        .map_err(|e| AppError::Command { command: format!("iw dev {} link", interface), message: e.to_string() })?;
+
+    Ok(parse_link(&String::from_utf8(output.stdout)?))
+}
+
+
+fn parse_link(link: &str) -> Option<Wifi> {
+    let mut ssid = None;
+    let mut signal = None;
+
+    for line in link.lines() {
+        match line.trim().split_once(':') {
+            Some(("SSID", value)) => ssid = Some(value.trim().to_string()),
+            Some(("signal", value)) => signal = value.split_whitespace().next().and_then(|s| s.parse().ok()),
+            _ => {},
+        }
+    }
+
+    Some(Wifi { ssid: ssid?, signal: signal? })
+}
+
+
+pub fn network(img: &mut DisplayImage, ctx: &Context) -> Result<(), Box<dyn std::error::Error>> {
+    let text = (ctx.net_info)(&ctx.ifname)?;
 
     let rect = Rect::at(0, 0).of_size(img.width(), img.height());
     render::draw_text(img, Color::rgb(0, 0, 0), rect, text.trim())?;
 
     Ok(())
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links() {
+        let link = "Connected to 12:34:56:78:9a:bc (on wlan0)\n\tSSID: Home: 2.4\n\tfreq: 2437.0\n\tsignal: -61 dBm\n\ttx bitrate: 72.2 MBit/s\n";
+        let wifi = parse_link(link).unwrap();
+
+        assert_eq!(wifi, Wifi { ssid: "Home: 2.4".to_string(), signal: -61 });
+        assert_eq!(wifi.bars(), "▂▄▆");
+        assert_eq!(parse_link("Not connected.\n"), None);
+    }
+}
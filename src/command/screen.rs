@@ -0,0 +1,484 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDateTime;
+use cosmic_text::Color;
+use image::imageops;
+use imageproc::rect::Rect;
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use qrcode::QrCode;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+
+use crate::{
+    app_error::AppError,
+    args::{Dither, Scaling},
+    data::{self, Source},
+    driver::{DisplayImage, BLACK, WHITE},
+    render,
+    vault::{collect_tasks, note_to_ast, Task},
+};
+
+use super::{widgets::Widget, Context};
+
+
+const PADDING: i32 = 2;
+const TEXT_SIZE: f32 = 16.0;
+// Modules of white around QR codes, less than the standard 4 but enough for phones
+const QR_QUIET: u32 = 2;
+
+/// Shown instead of a placeholder that can't be filled
+const MISSING: &str = "?";
+
+
+lazy_static! {
+    /// `{now}`, `{now:%H:%M}`, `{weather:current.temp}`
+    static ref RE_PLACEHOLDER: Regex = Regex::new(r"\{(?<name>[\w-]+)(?::(?<arg>[^}]*))?\}").unwrap();
+}
+
+
+/// Screen layout from a TOML file, regions are drawn in order:
+///
+/// ```toml
+/// [sources.weather]
+/// path = "weather.json"
+///
+/// [[region]]
+/// height = 24
+/// type = "text"
+/// text = "{now:%A, %d %B} · {weather:current.temp}°C"
+/// ```
+#[derive(Deserialize, Debug)]
+pub struct Screen {
+    #[serde(default)]
+    pub sources: HashMap<String, Source>,
+    #[serde(default, rename = "region")]
+    pub regions: Vec<Region>,
+}
+
+impl Screen {
+    /// Read the screen, with relative paths taken from its directory
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let s = std::fs::read_to_string(path).map_err(|e| AppError::Config { path: path.to_path_buf(), message: e.to_string() })?;
+        let mut screen: Self = toml::from_str(&s).map_err(|e| AppError::Config {
+            path: path.to_path_buf(),
+            message: format!("Wrong screen: {}", e),
+        })?;
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        for source in screen.sources.values_mut() {
+            *source = source.relative_to(dir);
+        }
+        for region in &mut screen.regions {
+            if let Item::Image { path, .. } = &mut region.item {
+                *path = dir.join(&*path);
+            }
+
+            region.item.check().map_err(|message| AppError::Config { path: path.to_path_buf(), message })?;
+        }
+
+        Ok(screen)
+    }
+}
+
+
+/// Part of the screen with something in it
+#[derive(Deserialize, Debug)]
+pub struct Region {
+    #[serde(default)]
+    pub x: Length,
+    #[serde(default)]
+    pub y: Length,
+    #[serde(default = "Length::full")]
+    pub width: Length,
+    #[serde(default = "Length::full")]
+    pub height: Length,
+    #[serde(default)]
+    pub border: bool,
+    #[serde(flatten)]
+    pub item: Item,
+}
+
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Item {
+    /// Text with placeholders: `{now}` or `{now:FORMAT}` for the time, `{tasks}` for the number of open tasks,
+    /// `{SOURCE:PATH}` for a value from a data source
+    Text {
+        text: String,
+        #[serde(default)]
+        size: Option<f32>,
+        #[serde(default)]
+        align: Align,
+        #[serde(default)]
+        accent: bool,
+    },
+    /// Tasks of today
+    Tasks {
+        #[serde(default)]
+        show: TaskFilter,
+        /// Only tasks with a time
+        #[serde(default)]
+        timed: bool,
+        /// Only tasks with this text in them, ignoring the case
+        #[serde(default)]
+        contains: Option<String>,
+        #[serde(default)]
+        limit: Option<usize>,
+        #[serde(default)]
+        size: Option<f32>,
+    },
+    /// The time in big digits, as big as the region allows
+    Clock {
+        #[serde(default = "Item::clock_format")]
+        format: String,
+    },
+    /// A widget with its label, see `command::widgets`
+    Value {
+        widget: Widget,
+        #[serde(default)]
+        size: Option<f32>,
+    },
+    Image {
+        path: PathBuf,
+        #[serde(default)]
+        fit: Scaling,
+        #[serde(default)]
+        dither: Dither,
+        #[serde(default = "Item::one")]
+        gamma: f32,
+        #[serde(default = "Item::one")]
+        contrast: f32,
+    },
+    /// QR code of the text, with the same placeholders as in `Text`
+    Qr {
+        text: String,
+    },
+}
+
+impl Item {
+    /// Formatting the time panics on a wrong format or on one a time without a zone can't fill,
+    /// like `%Z`, so they are tried on a sample time up front
+    fn check(&self) -> Result<(), String> {
+        let formats: Vec<&str> = match self {
+            Item::Clock { format } => vec![format],
+            Item::Text { text, .. } | Item::Qr { text } => RE_PLACEHOLDER.captures_iter(text)
+                .filter(|caps| &caps["name"] == "now")
+                .filter_map(|caps| caps.name("arg").map(|arg| arg.as_str()))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        match formats.into_iter().find(|format| write!(String::new(), "{}", NaiveDateTime::default().format(format)).is_err()) {
+            Some(format) => Err(format!("Wrong time format '{}'", format)),
+            None => Ok(()),
+        }
+    }
+
+    fn clock_format() -> String {
+        "%H:%M".to_string()
+    }
+
+    fn one() -> f32 {
+        1.0
+    }
+}
+
+
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TaskFilter {
+    #[default]
+    Unchecked,
+    Checked,
+    All,
+}
+
+
+/// Position or size in pixels, negative positions count from the right or the bottom, or in percent: `"50%"`
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Length {
+    Pixels(i32),
+    Percent(String),
+}
+
+impl Default for Length {
+    fn default() -> Self {
+        Self::Pixels(0)
+    }
+}
+
+impl Length {
+    fn full() -> Self {
+        Self::Percent("100%".to_string())
+    }
+
+    fn resolve(&self, total: u32) -> Result<i32, AppError> {
+        match self {
+            Self::Pixels(p) if *p < 0 => Ok(total as i32 + p),
+            Self::Pixels(p) => Ok(*p),
+            Self::Percent(s) => {
+                let percent: f32 = s.trim().trim_end_matches('%').trim().parse().map_err(|e| AppError::Data(
+                    format!("Wrong length '{}': {}", s, e)
+                ))?;
+                Ok((total as f32 * percent / 100.0).round() as i32)
+            },
+        }
+    }
+}
+
+
+impl Region {
+    /// Where the region is on the image, cut to fit
+    fn rect(&self, img: &DisplayImage) -> Result<Option<Rect>, AppError> {
+        let (w, h) = (img.width(), img.height());
+
+        let x = self.x.resolve(w)?.clamp(0, w as i32);
+        let y = self.y.resolve(h)?.clamp(0, h as i32);
+        let width = self.width.resolve(w)?.min(w as i32 - x);
+        let height = self.height.resolve(h)?.min(h as i32 - y);
+
+        if width <= 0 || height <= 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(Rect::at(x, y).of_size(width as u32, height as u32)))
+    }
+}
+
+
+/// Draw the regions of the screen described in the file
+pub fn screen(img: &mut DisplayImage, ctx: &Context, path: &Path, levels: u8) -> Result<(), Box<dyn std::error::Error>> {
+    info!("Loading screen from {}", path.display());
+
+    let screen = Screen::load(path)?;
+
+    for region in &screen.regions {
+        let Some(rect) = region.rect(img)? else {
+            warn!("Region is off the screen: {:?}", region);
+            continue;
+        };
+
+        debug!("Drawing {:?} at {:?}", region.item, rect);
+
+        if region.border {
+            render::draw_box(img, BLACK, rect);
+        }
+
+        // Keep the contents off the border
+        let inner = Rect::at(rect.left() + PADDING, rect.top() + PADDING)
+            .of_size(rect.width().saturating_sub(2 * PADDING as u32).max(1), rect.height().saturating_sub(2 * PADDING as u32).max(1));
+
+        draw_item(img, ctx, &screen, &region.item, inner, levels)?;
+    }
+
+    Ok(())
+}
+
+
+fn draw_item(img: &mut DisplayImage, ctx: &Context, screen: &Screen, item: &Item, rect: Rect, levels: u8) -> Result<(), Box<dyn std::error::Error>> {
+    match item {
+        Item::Text { text, size, align, accent } => {
+            let text = fill(text, ctx, &screen.sources);
+            let color = if *accent { render::TEXT_ACCENT } else { render::TEXT };
+            draw_aligned(img, rect, &[(&text, color)], size.unwrap_or(TEXT_SIZE), *align)?;
+        },
+        Item::Tasks { show, timed, contains, limit, size } => {
+            let mut lines = Vec::new();
+            for (depth, task) in today_tasks(ctx)? {
+                let shown = match show {
+                    TaskFilter::Unchecked => task.status.is_open(),
+                    TaskFilter::Checked => !task.status.is_open(),
+                    TaskFilter::All => true,
+                };
+                let matches = contains.as_ref().is_none_or(|c| task.text.to_lowercase().contains(&c.to_lowercase()));
+
+                if shown && matches && (!timed || task.time.is_some()) {
+                    let overdue = task.status.is_open() && task.ends(ctx.now.date()).is_some_and(|end| end < ctx.now);
+                    let text = format!("{}[{}] {}\n", " ".repeat(depth * 2), task.status.mark(), task.text.trim());
+                    lines.push((text, if overdue { render::TEXT_ACCENT } else { render::TEXT }));
+                }
+            }
+            lines.truncate(limit.unwrap_or(usize::MAX));
+
+            let spans: Vec<_> = lines.iter().map(|(text, color)| (text.as_str(), *color)).collect();
+            render::draw_rich_text_sized(img, rect, &spans, size.unwrap_or(TEXT_SIZE))?;
+        },
+        Item::Clock { format } => {
+            let text = ctx.now.format(format).to_string();
+            let size = rect.height() as f32 / 1.25;
+
+            // Narrow regions get smaller digits
+            let width = render::text_width(&text, size)?;
+            let size = if width > rect.width() { size * rect.width() as f32 / width as f32 } else { size };
+
+            let top = rect.top() + (rect.height() as f32 - size * 1.125) as i32 / 2;
+            let rect = Rect::at(rect.left(), top.max(rect.top())).of_size(rect.width(), rect.height());
+            render::draw_text_centered(img, render::TEXT, rect, &text, size)?;
+        },
+        Item::Value { widget, size } => {
+            let (text, stale) = widget.show(&screen.sources, ctx);
+            let size = size.unwrap_or(TEXT_SIZE);
+
+            if let Some(label) = &widget.label {
+                render::draw_rich_text_sized(img, rect, &[(label, render::TEXT)], size)?;
+            }
+
+            super::widgets::draw_value(img, rect, &text, stale, size)?;
+        },
+        Item::Image { path, fit, dither, gamma, contrast } => {
+            info!("Loading picture from {}", path.display());
+
+            let picture = super::image::prepare(image::open(path)?.to_luma32f(), (rect.width(), rect.height()), *fit, *gamma, *contrast);
+
+            // Drawn on its own so that whatever doesn't fit is cut at the region
+            let mut part = DisplayImage::from_pixel(rect.width(), rect.height(), WHITE);
+            let x = (rect.width() as i32 - picture.width() as i32) / 2;
+            let y = (rect.height() as i32 - picture.height() as i32) / 2;
+            render::draw_picture(&mut part, &picture, (x, y), levels, *dither);
+
+            imageops::replace(img, &part, rect.left() as i64, rect.top() as i64);
+        },
+        Item::Qr { text } => {
+            let text = fill(text, ctx, &screen.sources);
+            let code = QrCode::new(text.as_bytes()).map_err(|e| AppError::Data(
+                format!("Can't make a QR code of '{}': {}", text, e)
+            ))?;
+
+            let modules = code.width() as u32;
+            let module = rect.width().min(rect.height()) / (modules + 2 * QR_QUIET);
+            if module == 0 {
+                return Err(AppError::Data(format!("No room for a QR code of {} modules in {:?}", modules, rect)).into());
+            }
+
+            let side = module * modules;
+            let left = rect.left() + (rect.width() - side) as i32 / 2;
+            let top = rect.top() + (rect.height() - side) as i32 / 2;
+
+            for (i, color) in code.to_colors().into_iter().enumerate() {
+                if color == qrcode::Color::Dark {
+                    let (x, y) = (i as u32 % modules, i as u32 / modules);
+                    let at = Rect::at(left + (x * module) as i32, top + (y * module) as i32).of_size(module, module);
+                    render::fill_box(img, BLACK, at);
+                }
+            }
+        },
+    }
+
+    Ok(())
+}
+
+
+/// Draw the spans in one line aligned in `rect`
+fn draw_aligned(img: &mut DisplayImage, rect: Rect, spans: &[(&str, Color)], size: f32, align: Align) -> Result<(), Box<dyn std::error::Error>> {
+    let text: String = spans.iter().map(|(text, _)| *text).collect();
+    let free = rect.width() as i32 - render::text_width(&text, size)? as i32;
+
+    let left = match align {
+        Align::Left => 0,
+        Align::Center => free.max(0) / 2,
+        Align::Right => free.max(0),
+    };
+
+    let rect = Rect::at(rect.left() + left, rect.top()).of_size(rect.width() - left as u32, rect.height());
+    render::draw_rich_text_sized(img, rect, spans, size)
+}
+
+
+/// Replace the placeholders in the text
+fn fill(text: &str, ctx: &Context, sources: &HashMap<String, Source>) -> String {
+    RE_PLACEHOLDER.replace_all(text, |caps: &Captures| {
+        let arg = caps.name("arg").map(|m| m.as_str());
+
+        let value = match &caps["name"] {
+            "now" => Some(ctx.now.format(arg.unwrap_or("%H:%M")).to_string()),
+            "tasks" => today_tasks(ctx).ok().map(|tasks| tasks.iter().filter(|(_, task)| task.status.is_open()).count().to_string()),
+            name => sources.get(name)
+                .and_then(|source| source.read().map_err(|e| warn!("Could not read '{}': {}", name, e)).ok())
+                .and_then(|data| data.get(arg.unwrap_or("")).map(data::to_text)),
+        };
+
+        value.unwrap_or_else(|| {
+            warn!("Nothing for {}", &caps[0]);
+            MISSING.to_string()
+        })
+    }).into_owned()
+}
+
+
+/// All the tasks of today with their depth, parents first
+fn today_tasks(ctx: &Context) -> Result<Vec<(usize, Task)>, Box<dyn std::error::Error>> {
+    let note = match ctx.vault.read_note(ctx.now.date()) {
+        Ok(note) => note,
+        Err(e) => {
+            warn!("Could not open today note: {}", e);
+            return Ok(Vec::new());
+        },
+    };
+
+    let tasks = collect_tasks(&note_to_ast(&note), &note)?;
+
+    let mut flat = Vec::new();
+    flatten(tasks, 0, &mut flat);
+
+    Ok(flat)
+}
+
+
+fn flatten(tasks: Vec<Task>, depth: usize, flat: &mut Vec<(usize, Task)>) {
+    for mut task in tasks {
+        let subtasks = std::mem::take(&mut task.subtasks);
+        flat.push((depth, task));
+        flatten(subtasks, depth + 1, flat);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrong_time_format() {
+        let dir = std::env::temp_dir().join(format!("pinterface-screen-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for (region, ok) in [
+            ("type = 'clock'\nformat = '%H:%M'", true),
+            ("type = 'clock'\nformat = '%H:%Q'", false),
+            ("type = 'text'\ntext = '{now:%A} {weather:%}'", true),
+            ("type = 'text'\ntext = '{now:%}'", false),
+            ("type = 'qr'\ntext = '{now:%Y %!}'", false),
+            ("type = 'clock'\nformat = '%H:%M %Z'", false),
+            ("type = 'text'\ntext = '{now:%z}'", false),
+            ("type = 'text'\ntext = '{now:%:z}'", false),
+        ] {
+            let path = dir.join("screen.toml");
+            std::fs::write(&path, format!("[[region]]\n{}\n", region)).unwrap();
+
+            match Screen::load(&path) {
+                Ok(_) => assert!(ok, "{}", region),
+                Err(e) => {
+                    assert!(!ok, "{}: {}", region, e);
+                    assert_eq!(e.category(), "Config invalid");
+                },
+            }
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
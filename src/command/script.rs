@@ -0,0 +1,166 @@
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use image::DynamicImage;
+use imageproc::rect::Rect;
+use log::{info, warn};
+
+use crate::{args::TextStyle, driver::DisplayImage, render};
+
+
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+const MARGIN: i32 = 4;
+// How often the command is checked for being done
+const POLL_MS: u64 = 20;
+
+
+/// What the command printed
+pub enum Output {
+    Text(String),
+    Picture(DynamicImage),
+}
+
+
+/// Why the command didn't give anything to show
+#[derive(Debug)]
+pub enum Failure {
+    Spawn(std::io::Error),
+    TimedOut { after: Duration, stderr: String },
+    Exited { status: ExitStatus, stderr: String },
+    Picture(image::ImageError),
+}
+
+impl Failure {
+    /// Short description for the title of the error panel
+    pub fn title(&self) -> String {
+        match self {
+            Self::Spawn(e) => format!("Could not start: {}", e),
+            Self::TimedOut { after, .. } => format!("Timed out after {}s", after.as_secs_f32()),
+            Self::Exited { status, .. } => match status.code() {
+                Some(code) => format!("Exited with {}", code),
+                None => format!("Stopped: {}", status),
+            },
+            Self::Picture(_) => "Wrong PNG".to_string(),
+        }
+    }
+
+    pub fn details(&self) -> String {
+        match self {
+            Self::Spawn(_) => String::new(),
+            Self::TimedOut { stderr, .. } | Self::Exited { stderr, .. } => stderr.trim().to_string(),
+            Self::Picture(e) => e.to_string(),
+        }
+    }
+}
+
+
+/// Run the command with `sh -c`, killing it when it takes longer than `timeout`
+/// to exit and close its output
+pub fn run(command: &str, timeout: Duration) -> Result<Output, Failure> {
+    info!("Running '{}'", command);
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Its own group, so that whatever it starts can be stopped with it
+        .process_group(0)
+        .spawn()
+        .map_err(Failure::Spawn)?;
+
+    // Read in the background, a full pipe would block the command forever
+    let read = |mut pipe: Box<dyn Read + Send>| thread::spawn(move || {
+        let mut buffer = Vec::new();
+        pipe.read_to_end(&mut buffer).map(|_| buffer)
+    });
+    let stdout = read(Box::new(child.stdout.take().unwrap()));
+    let stderr = read(Box::new(child.stderr.take().unwrap()));
+
+    let collect = |reader: thread::JoinHandle<std::io::Result<Vec<u8>>>| reader.join().ok().and_then(|r| r.ok()).unwrap_or_default();
+
+    let deadline = Instant::now() + timeout;
+    let mut exited = None;
+    let status = loop {
+        if exited.is_none() {
+            exited = child.try_wait().map_err(Failure::Spawn)?;
+        }
+
+        // Whatever it left running in the background can still hold the output open
+        if let Some(status) = exited.filter(|_| stdout.is_finished() && stderr.is_finished()) {
+            break status;
+        }
+
+        if Instant::now() >= deadline {
+            warn!("'{}' took longer than {:?}", command, timeout);
+            let group = format!("-{}", child.id());
+            if Command::new("kill").args(["-KILL", "--", &group]).status().is_err() {
+                child.kill().map_err(Failure::Spawn)?;
+            }
+            child.wait().map_err(Failure::Spawn)?;
+
+            let stderr = String::from_utf8_lossy(&collect(stderr)).into_owned();
+            return Err(Failure::TimedOut { after: timeout, stderr });
+        }
+
+        thread::sleep(Duration::from_millis(POLL_MS));
+    };
+
+    let stdout = collect(stdout);
+    let stderr = String::from_utf8_lossy(&collect(stderr)).into_owned();
+
+    if !status.success() {
+        return Err(Failure::Exited { status, stderr });
+    }
+
+    if stdout.starts_with(PNG_MAGIC) {
+        let picture = image::load_from_memory_with_format(&stdout, image::ImageFormat::Png).map_err(Failure::Picture)?;
+        return Ok(Output::Picture(picture));
+    }
+
+    Ok(Output::Text(String::from_utf8_lossy(&stdout).into_owned()))
+}
+
+
+/// Draw what the command prints, or why it failed
+pub fn script(img: &mut DisplayImage, command: &str, timeout: Duration, style: TextStyle, picture: &super::image::Options) -> Result<(), Box<dyn std::error::Error>> {
+    match run(command, timeout) {
+        Ok(Output::Text(text)) => text_styled(img, text.trim_end(), style),
+        Ok(Output::Picture(output)) => super::image::picture(img, output, picture),
+        Err(failure) => {
+            warn!("'{}' failed: {:?}", command, failure);
+            super::message::error(img, &failure.title(), &format!("$ {}\n{}", command, failure.details()))
+        },
+    }
+}
+
+
+fn text_styled(img: &mut DisplayImage, text: &str, style: TextStyle) -> Result<(), Box<dyn std::error::Error>> {
+    let rect = Rect::at(MARGIN, MARGIN).of_size(img.width() - 2 * MARGIN as u32, img.height() - 2 * MARGIN as u32);
+
+    match style {
+        TextStyle::Small => render::draw_rich_text_sized(img, rect, &[(text, render::TEXT)], 12.0),
+        TextStyle::Normal => render::draw_rich_text_sized(img, rect, &[(text, render::TEXT)], 16.0),
+        TextStyle::Large => render::draw_rich_text_sized(img, rect, &[(text, render::TEXT)], 24.0),
+        TextStyle::Big => {
+            // As big as the panel allows, centered both ways
+            let lines = text.lines().count().max(1) as f32;
+            let size = rect.height() as f32 / lines / 1.125;
+
+            let width = render::text_width(text, size)?;
+            if width == 0 {
+                return Ok(());
+            }
+            let size = size.min(size * rect.width() as f32 / width as f32);
+
+            let top = rect.top() + (rect.height() as f32 - size * 1.125 * lines) as i32 / 2;
+            let rect = Rect::at(rect.left(), top).of_size(rect.width(), rect.height());
+            render::draw_text_centered(img, render::TEXT, rect, text, size)
+        },
+    }
+}
@@ -0,0 +1,125 @@
+use cosmic_text::Color;
+use imageproc::rect::Rect;
+use log::debug;
+use markdown::mdast::Node;
+
+use crate::{app_error::AppError, driver::DisplayImage, render, vault::{self, note_to_ast}};
+
+use super::Context;
+
+
+/// Spans of one line on the screen
+type Line = Vec<(String, Color)>;
+
+
+/// The part of today's note under the heading at `path`, e.g. `Plan > Morning`
+pub fn section(img: &mut DisplayImage, ctx: &Context, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let note = ctx.vault.read_note(ctx.now.date())?;
+
+    let ast = note_to_ast(&note);
+    let section = vault::section(&ast, path).ok_or(AppError::Data(format!("No section '{}' in today note", path)))?;
+
+    debug!("Found section '{}':\n{:#?}", section.title, section.blocks);
+
+    let mut lines = vec![vec![(section.title, render::TEXT_ACCENT)]];
+    lines.extend(blocks(section.blocks));
+
+    let mut text = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            text.push(("\n", render::TEXT));
+        }
+        text.extend(line.iter().map(|(s, color)| (s.as_str(), *color)));
+    }
+
+    let rect = Rect::at(0, 0).of_size(img.width(), img.height());
+    render::draw_rich_text(img, rect, &text)
+}
+
+
+fn blocks(nodes: &[Node]) -> Vec<Line> {
+    nodes.iter().flat_map(block).collect()
+}
+
+
+fn block(node: &Node) -> Vec<Line> {
+    match node {
+        Node::Heading(_) => vec![vec![(node.to_string(), render::TEXT_ACCENT)]],
+        Node::Paragraph(paragraph) => {
+            let mut lines = vec![Vec::new()];
+            inline(&paragraph.children, render::TEXT, &mut lines);
+            lines
+        },
+        Node::List(list) => {
+            let mut lines = Vec::new();
+            for (i, item) in list.children.iter().enumerate() {
+                let Node::ListItem(item) = item else {
+                    continue;
+                };
+
+                let marker = match item.checked {
+                    Some(true) => "☑ ".to_string(),
+                    Some(false) => "☐ ".to_string(),
+                    None if list.ordered => format!("{}. ", list.start.unwrap_or(1) + i as u32),
+                    None => "• ".to_string(),
+                };
+
+                let mut item_lines = blocks(&item.children);
+                if item_lines.is_empty() {
+                    item_lines.push(Vec::new());
+                }
+                prefix(&mut item_lines, &marker, &" ".repeat(marker.chars().count()));
+                lines.extend(item_lines);
+            }
+            lines
+        },
+        Node::BlockQuote(quote) => {
+            let mut lines = blocks(&quote.children);
+            prefix(&mut lines, "│ ", "│ ");
+            lines
+        },
+        Node::Code(code) => code.value.lines().map(|line| vec![(line.to_string(), render::TEXT)]).collect(),
+        Node::ThematicBreak(_) | Node::Html(_) | Node::Definition(_) => Vec::new(),
+        node => {
+            debug!("Showing {:?} as plain text", node);
+            vec![vec![(node.to_string(), render::TEXT)]]
+        },
+    }
+}
+
+
+/// Add inline text to the last line, starting new ones at line breaks. Bold text stands out.
+fn inline(nodes: &[Node], color: Color, lines: &mut Vec<Line>) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => push_text(lines, &text.value, color),
+            Node::InlineCode(code) => push_text(lines, &code.value, color),
+            Node::Break(_) => lines.push(Vec::new()),
+            Node::Strong(strong) => inline(&strong.children, render::TEXT_ACCENT, lines),
+            node => match node.children() {
+                Some(children) => inline(children, color, lines),
+                None => push_text(lines, &node.to_string(), color),
+            },
+        }
+    }
+}
+
+
+fn push_text(lines: &mut Vec<Line>, text: &str, color: Color) {
+    for (i, part) in text.split('\n').enumerate() {
+        if i > 0 {
+            lines.push(Vec::new());
+        }
+        if let Some(line) = lines.last_mut() {
+            line.push((part.to_string(), color));
+        }
+    }
+}
+
+
+/// Put `first` before the first line and `rest` before the others
+fn prefix(lines: &mut [Line], first: &str, rest: &str) {
+    for (i, line) in lines.iter_mut().enumerate() {
+        line.insert(0, (if i == 0 { first } else { rest }.to_string(), render::TEXT));
+    }
+}
@@ -0,0 +1,57 @@
+use chrono::{DateTime, Local};
+use imageproc::rect::Rect;
+use log::warn;
+
+use crate::{args::DisplayMode, data, driver::{DisplayImage, BLACK}, render, vault::Changes};
+
+use super::{sync::Problem, Context};
+
+
+/// Height of the bar at the bottom of the panel
+pub const HEIGHT: u32 = 16;
+const SIZE: f32 = 12.0;
+const MARGIN: i32 = 3;
+
+
+/// Where the views are drawn when there is a status bar
+pub fn view_rect(img: &DisplayImage) -> Rect {
+    Rect::at(0, 0).of_size(img.width(), img.height() - HEIGHT)
+}
+
+
+/// Wi-Fi, how long ago the vault changed, the time of this refresh and the display mode, along the bottom.
+/// The first sync problem, if any, takes the place of the vault change. `changes` is `None` when the vault could not be read.
+pub fn status_bar(img: &mut DisplayImage, ctx: &Context, mode: DisplayMode, problems: &[Problem], changes: Option<&Changes>) -> Result<(), Box<dyn std::error::Error>> {
+    let top = (img.height() - HEIGHT) as i32;
+    render::draw_line(img, BLACK, (0, top), (img.width() as i32 - 1, top));
+
+    let (wifi, wifi_color) = match (ctx.wifi_info)(&ctx.ifname) {
+        Ok(Some(wifi)) => (format!("{} {}", wifi.bars(), wifi.ssid), render::TEXT),
+        Ok(None) => ("No Wi-Fi".to_string(), render::TEXT_ACCENT),
+        Err(e) => {
+            warn!("Could not get the Wi-Fi: {}", e);
+            ("Wi-Fi ?".to_string(), render::TEXT_ACCENT)
+        },
+    };
+
+    let (synced, synced_color) = match (problems.first(), changes.map(|changes| changes.modified)) {
+        (Some(problem), _) => (format!("! {}", problem), render::TEXT_ACCENT),
+        (None, Some(Some(modified))) => (format!("sync {}", data::format_age(ctx.now - DateTime::<Local>::from(modified).naive_local())), render::TEXT),
+        (None, Some(None)) => ("sync never".to_string(), render::TEXT),
+        (None, None) => ("sync ?".to_string(), render::TEXT),
+    };
+
+    let refreshed = format!("{} {}", ctx.now.format("%H:%M"), mode);
+
+    let width = img.width() - 2 * MARGIN as u32;
+    let rect = Rect::at(MARGIN, top + 1).of_size(width, HEIGHT - 1);
+    render::draw_rich_text_sized(img, rect, &[(&wifi, wifi_color)], SIZE)?;
+
+    let synced_width = render::text_width(&synced, SIZE)?;
+    let rect = Rect::at((img.width() - synced_width) as i32 / 2, top + 1).of_size(synced_width + 1, HEIGHT - 1);
+    render::draw_rich_text_sized(img, rect, &[(&synced, synced_color)], SIZE)?;
+
+    let refreshed_width = render::text_width(&refreshed, SIZE)?;
+    let rect = Rect::at(img.width() as i32 - MARGIN - refreshed_width as i32, top + 1).of_size(refreshed_width + 1, HEIGHT - 1);
+    render::draw_rich_text_sized(img, rect, &[(&refreshed, render::TEXT)], SIZE)
+}
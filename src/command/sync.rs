@@ -0,0 +1,168 @@
+use std::process::Command;
+
+use chrono::{DateTime, Duration, Local};
+use imageproc::rect::Rect;
+use log::warn;
+
+use crate::{app_error::AppError, data, driver::{DisplayImage, ACCENT}, render, vault::Changes};
+
+use super::Context;
+
+
+const BANNER_SIZE: f32 = 12.0;
+// Height of a line of the banner
+const BANNER_LINE: u32 = 14;
+
+/// Unit states that don't need a warning
+const RUNNING: &[&str] = &["active", "activating", "reloading"];
+
+
+/// Why the vault on the panel may be out of date
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// The sync service is not running, with the state systemd gives
+    Unit(String),
+    /// Nothing in the vault changed for this long
+    Stale(Duration),
+    /// Notes Obsidian Sync could not merge
+    Conflicts(usize),
+    /// The vault could not be looked through for the other problems
+    Unreadable,
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unit(state) => write!(f, "sync {}", state),
+            Self::Stale(age) => write!(f, "no sync for {}", data::format_age(*age)),
+            Self::Conflicts(1) => write!(f, "1 sync conflict"),
+            Self::Conflicts(n) => write!(f, "{} sync conflicts", n),
+            Self::Unreadable => write!(f, "vault unreadable"),
+        }
+    }
+}
+
+
+/// State of a systemd unit, e.g. `active` or `failed`
+pub fn unit_state(unit: &str) -> Result<String, AppError> {
+    let output = Command::new("systemctl")
+        .args(["is-active", unit])
+        .output()
+        .map_err(|e| AppError::Command { command: format!("systemctl is-active {}", unit), message: e.to_string() })?;
+
+    // Exits with an error for anything but active, the state is printed either way
+    let state = String::from_utf8(output.stdout)?.trim().to_string();
+    if state.is_empty() {
+        return Err(AppError::Command {
+            command: format!("systemctl is-active {}", unit),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+
+    Ok(state)
+}
+
+
+/// What looks wrong with the sync: the unit not running, no changes for longer than `max_age`, conflicts.
+/// `changes` is `None` when the vault could not be read.
+pub fn check(ctx: &Context, unit: &str, max_age: Duration, changes: Option<&Changes>) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    match (ctx.unit_state)(unit) {
+        Ok(state) if RUNNING.contains(&state.as_str()) => {},
+        Ok(state) => problems.push(Problem::Unit(state)),
+        Err(e) => {
+            warn!("Could not get the state of {}: {}", unit, e);
+            problems.push(Problem::Unit("unknown".to_string()));
+        },
+    }
+
+    let Some(changes) = changes else {
+        problems.push(Problem::Unreadable);
+        return problems;
+    };
+
+    if let Some(modified) = changes.modified {
+        let age = ctx.now - DateTime::<Local>::from(modified).naive_local();
+        if age > max_age {
+            problems.push(Problem::Stale(age));
+        }
+    }
+
+    if !changes.conflicts.is_empty() {
+        warn!("Sync conflicts: {:?}", changes.conflicts);
+        problems.push(Problem::Conflicts(changes.conflicts.len()));
+    }
+
+    problems
+}
+
+
+/// The problems on an accent strip along the bottom of the panel, a line each
+pub fn banner(img: &mut DisplayImage, problems: &[Problem]) -> Result<(), Box<dyn std::error::Error>> {
+    let text = problems.iter().map(|problem| format!("! {}", problem)).collect::<Vec<_>>().join("\n");
+    let height = BANNER_LINE * problems.len() as u32 + 2;
+
+    let top = (img.height() - height) as i32;
+    render::fill_box(img, ACCENT, Rect::at(0, top).of_size(img.width(), height));
+
+    let rect = Rect::at(3, top + 1).of_size(img.width() - 6, height - 1);
+    render::draw_rich_text_sized(img, rect, &[(&text, render::TEXT_INVERTED)], BANNER_SIZE)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::vault::Vault;
+
+    use super::*;
+
+    fn failed(_unit: &str) -> Result<String, AppError> {
+        Ok("failed".to_string())
+    }
+
+    fn active(_unit: &str) -> Result<String, AppError> {
+        Ok("active".to_string())
+    }
+
+    #[test]
+    fn problems() {
+        let root = std::env::temp_dir().join(format!("pinterface-sync-{}", std::process::id()));
+        std::fs::create_dir_all(root.join(".obsidian")).unwrap();
+        std::fs::create_dir_all(root.join("Periodic")).unwrap();
+        std::fs::write(root.join(".obsidian/workspace (conflict 2026-10-16 08-00).md"), "").unwrap();
+
+        let now = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap().and_hms_opt(11, 20, 0).unwrap();
+        let touch = |name: &str, age: Duration| {
+            let file = std::fs::File::create(root.join(name)).unwrap();
+            file.set_modified((now - age).and_local_timezone(Local).unwrap().into()).unwrap();
+        };
+        touch("Periodic/20261016.md", Duration::days(2));
+
+        let mut ctx = Context {
+            now,
+            vault: Vault::new(&root),
+            ifname: "wlan0".to_string(),
+            net_info: |_| Ok(String::new()),
+            wifi_info: |_| Ok(None),
+            unit_state: active,
+        };
+
+        let problems = |ctx: &Context, max_age| check(ctx, "obsidian-sync", max_age, ctx.vault.changes().ok().as_ref());
+        assert_eq!(problems(&ctx, Duration::hours(72)), vec![]);
+
+        // A conflict is a change too, hidden folders don't count
+        touch("Periodic/20261016 (conflict 2026-10-17 21-04).md", Duration::days(1));
+        ctx.unit_state = failed;
+
+        let found = problems(&ctx, Duration::hours(12));
+        assert_eq!(found, vec![Problem::Unit("failed".to_string()), Problem::Stale(Duration::days(1)), Problem::Conflicts(1)]);
+        assert_eq!(found.iter().map(Problem::to_string).collect::<Vec<_>>(), ["sync failed", "no sync for 1d", "1 sync conflict"]);
+
+        // A vault that can't be read hides the other problems
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(problems(&ctx, Duration::hours(12)), vec![Problem::Unit("failed".to_string()), Problem::Unreadable]);
+    }
+}
@@ -1,48 +1,192 @@
+use chrono::NaiveDateTime;
 use cosmic_text::Color;
 use imageproc::rect::Rect;
-use log::{debug, warn};
+use log::debug;
 
-use crate::{driver::DisplayImage, render, vault::{collect_tasks, note_to_ast, read_today_note, Task}};
+use crate::{driver::{DisplayImage, BLACK}, render, vault::{self, filter_tasks, note_to_ast, Note, Property, Status, Task, TaskCounts}};
 
+use super::Context;
 
-const TIME_PRETTY: &'static str = "<~ UwU ~> %Y-%m-%d %H:%M";
 
+const TIME_PRETTY: &str = "<~ UwU ~> %Y-%m-%d %H:%M";
 
-pub fn tasks(img: &mut DisplayImage) -> Result<(), Box<dyn std::error::Error>> {
-    let now = chrono::Local::now();
+const LINE_HEIGHT: u32 = 18;
+// Space around the progress bars
+const GAP: u32 = 6;
 
-    match read_today_note() {
-        Err(e) => {
-            warn!("Could not open today note: {}", e);
-        },
-        Ok(note) => {
-            let ast = note_to_ast(&note);
-            let tasks = collect_tasks(&ast, &note, true)?;
 
-            debug!("Found unchecked tasks:\n{:#?}", tasks);
+/// What the tasks view lists and how
+pub struct Options<'a> {
+    /// Properties of the note shown above the tasks
+    pub properties: &'a [String],
+    /// Headings with a progress bar under the one of the day
+    pub headings: &'a [String],
+    /// Only tasks under one of these headings are listed, when there are any
+    pub sections: &'a [String],
+    /// Only tasks with one of these statuses are listed
+    pub statuses: &'a [Status],
+    /// Put the tasks of each group in the order of `statuses`
+    pub by_status: bool,
+}
+
+
+/// Today's tasks grouped by heading, with how many are done, under the chosen properties of the note
+pub fn tasks(img: &mut DisplayImage, ctx: &Context, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let Options { properties, headings, sections, statuses, by_status } = *options;
+    let now = ctx.now;
+
+    let source = ctx.vault.read_note(now.date())?;
+    let note = Note::parse(&source)?;
 
-            let text = format!("{}\n{}", now.format(TIME_PRETTY), format_tasks(tasks, 0));
+    let rect = Rect::at(0, 0).of_size(img.width(), LINE_HEIGHT);
+    render::draw_text(img, render::TEXT, rect, &now.format(TIME_PRETTY).to_string())?;
 
-            let rect = Rect::at(0, 0).of_size(img.width(), img.height());
-            render::draw_text(img, Color::rgb(0, 0, 0), rect, &text)?;
-        },
+    let mut progress = Vec::new();
+    let today = TaskCounts::of(&note.tasks);
+    if today.total > 0 {
+        progress.push(("Today".to_string(), today));
+
+        let ast = note_to_ast(&source);
+        for heading in headings {
+            match vault::section(&ast, heading) {
+                Some(section) => progress.push((section.title.clone(), TaskCounts::of(&section.tasks(&source)?))),
+                None => debug!("No section '{}' for progress", heading),
+            }
+        }
+    }
+
+    let top = LINE_HEIGHT * (1 + progress.len() as u32);
+    draw_progress(img, LINE_HEIGHT as i32, &progress)?;
+
+    let mut lines = Vec::new();
+    format_properties(&mut lines, &note, properties);
+
+    let tasks: Vec<_> = filter_tasks(note.tasks, &|task| statuses.contains(&task.status)).into_iter()
+        .filter(|task| sections.is_empty() || sections.iter().any(|section| task.is_under(section)))
+        .collect();
+    let tasks = if by_status { sort_by_status(tasks, statuses) } else { tasks };
+    debug!("Found tasks:\n{:#?}", tasks);
+    format_groups(&mut lines, tasks, now);
+
+    let spans: Vec<_> = lines.iter().map(|(text, color)| (text.as_str(), *color)).collect();
+
+    let rect = Rect::at(0, top as i32).of_size(img.width(), img.height().saturating_sub(top).max(1));
+    render::draw_rich_text(img, rect, &spans)
+}
+
+
+/// A row per label: the label, a bar, and `done/total done`, with the bars lined up
+fn draw_progress(img: &mut DisplayImage, top: i32, rows: &[(String, TaskCounts)]) -> Result<(), Box<dyn std::error::Error>> {
+    let counts: Vec<_> = rows.iter().map(|(_, c)| format!("{}/{} done", c.done, c.total)).collect();
+
+    let mut label_width = 0;
+    let mut count_width = 0;
+    for ((label, _), count) in rows.iter().zip(&counts) {
+        label_width = label_width.max(render::text_width(label, 16.0)?);
+        count_width = count_width.max(render::text_width(count, 16.0)?);
+    }
+
+    let bar_left = label_width + GAP;
+    let bar_width = img.width().saturating_sub(bar_left + count_width + 2 * GAP);
+
+    for (i, ((label, counts), count)) in rows.iter().zip(&counts).enumerate() {
+        let y = top + (i as u32 * LINE_HEIGHT) as i32;
+
+        render::draw_text(img, render::TEXT, Rect::at(0, y).of_size(label_width, LINE_HEIGHT), label)?;
+
+        if bar_width > 2 {
+            let bar = Rect::at(bar_left as i32, y + GAP as i32 / 2 + 1).of_size(bar_width, LINE_HEIGHT - GAP);
+            render::draw_progress(img, BLACK, bar, counts.done, counts.total);
+        }
+
+        let left = img.width() - count_width;
+        render::draw_text(img, render::TEXT, Rect::at(left as i32, y).of_size(count_width, LINE_HEIGHT), count)?;
     }
 
     Ok(())
 }
 
 
-fn format_tasks(tasks: Vec<Task>, depth: u8) -> String {
-    let mut result = String::new();
+fn format_properties(lines: &mut Vec<(String, Color)>, note: &Note, names: &[String]) {
+    for name in names {
+        match note.property(name) {
+            None | Some(Property::Empty) => debug!("No property '{}' in the note", name),
+            Some(value) => lines.push((format!("{}: {}\n", label(name), value), render::TEXT_ACCENT)),
+        }
+    }
+}
+
+
+/// `focus` as `Focus`
+fn label(name: &str) -> String {
+    let name = name.trim();
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+
+/// Put the tasks in the order of `statuses`, keeping the groups of `format_groups` together
+fn sort_by_status(tasks: Vec<Task>, statuses: &[Status]) -> Vec<Task> {
+    let mut groups = Vec::new();
+
+    let mut keyed: Vec<_> = tasks.into_iter()
+        .map(|task| {
+            let group = (task.headings.clone(), task.parent.clone());
+            let i = groups.iter().position(|g| *g == group).unwrap_or_else(|| {
+                groups.push(group);
+                groups.len() - 1
+            });
+            ((i, statuses.iter().position(|s| *s == task.status)), task)
+        })
+        .collect();
+
+    keyed.sort_by_key(|(key, _)| *key);
+    keyed.into_iter().map(|(_, task)| task).collect()
+}
+
+
+/// Tasks under the heading they are in, and under the plain list item they are in indented
+fn format_groups(lines: &mut Vec<(String, Color)>, tasks: Vec<Task>, now: NaiveDateTime) {
+    let mut heading = None;
+    let mut parent = None;
 
     for task in tasks {
-        for _ in 0..depth {
-            result.push_str(" ");
+        if task.headings.last() != heading.as_ref() {
+            heading = task.headings.last().cloned();
+            parent = None;
+            if let Some(heading) = &heading {
+                lines.push((format!("# {}\n", heading), render::TEXT));
+            }
+        }
+
+        if task.parent != parent {
+            parent = task.parent.clone();
+            if let Some(parent) = &parent {
+                lines.push((format!("{}\n", parent), render::TEXT));
+            }
         }
-        let text = format!("- {}\n", task.text);
-        result.push_str(&text);
-        result.push_str(&format_tasks(task.subtasks, depth + 2));
+
+        let depth = if parent.is_some() { 2 } else { 0 };
+        format_tasks(lines, vec![task], depth, now);
     }
+}
+
 
-    result
+fn format_tasks(lines: &mut Vec<(String, Color)>, tasks: Vec<Task>, depth: u8, now: NaiveDateTime) {
+    for task in tasks {
+        let mut text = String::new();
+        for _ in 0..depth {
+            text.push(' ');
+        }
+        text.push_str(&format!("{} {}\n", task.status.glyph(), task.text));
+
+        // Tasks that should have been done by now stand out
+        let overdue = task.ends(now.date()).is_some_and(|end| end < now);
+        lines.push((text, if overdue { render::TEXT_ACCENT } else { render::TEXT }));
+
+        format_tasks(lines, task.subtasks, depth + 2, now);
+    }
 }
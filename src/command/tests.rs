@@ -0,0 +1,377 @@
+//! Golden image tests: every view is drawn against the fixture vault at a frozen time
+//! and compared to the PNGs in `tests/golden`.
+//!
+//! Text is drawn with the font in `tests/fixtures/fonts` only.
+//! A missing golden image fails the test, `UPDATE_GOLDEN=1 cargo test` writes new ones
+//! and rewrites the others. When a view doesn't match, the difference is saved to `target/golden-diff`.
+
+use std::path::PathBuf;
+
+use chrono::{Duration, NaiveDate};
+use image::{DynamicImage, GrayImage, Luma, Rgb, RgbImage};
+
+use crate::{
+    app_error::AppError,
+    args::{Dither, DisplayMode, Pattern, Scaling, TextStyle},
+    driver::{panel::EPD_2IN7_V2, DisplayImage, WHITE},
+    vault::{Status, Vault},
+};
+
+use super::{network::Wifi, sync::Problem, tasks::Options, Context};
+
+
+/// Channel difference that counts a pixel as changed, fonts render slightly differently across systems
+const PIXEL_TOLERANCE: u8 = 64;
+
+/// Share of changed pixels that still matches
+const IMAGE_TOLERANCE: f32 = 0.005;
+
+/// Statuses the tasks view lists by default
+const OPEN: &[Status] = &[Status::Todo, Status::InProgress, Status::Important, Status::Question];
+
+/// The tasks view without any options
+const TASKS: Options = Options { properties: &[], headings: &[], sections: &[], statuses: OPEN, by_status: false };
+
+
+fn root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+
+fn fake_net_info(interface: &str) -> Result<String, AppError> {
+    Ok(format!("{}: 192.168.1.23/24\nfe80::ba27:ebff:fe12:3456/64", interface))
+}
+
+
+fn fake_wifi_info(_interface: &str) -> Result<Option<Wifi>, AppError> {
+    Ok(Some(Wifi { ssid: "Home".to_string(), signal: -58 }))
+}
+
+
+fn fake_unit_state(_unit: &str) -> Result<String, AppError> {
+    Ok("active".to_string())
+}
+
+
+fn context() -> Context {
+    Context {
+        now: NaiveDate::from_ymd_opt(2026, 10, 18).unwrap().and_hms_opt(11, 20, 0).unwrap(),
+        vault: Vault::new(root().join("tests/fixtures/vault")),
+        ifname: "wlan0".to_string(),
+        net_info: fake_net_info,
+        wifi_info: fake_wifi_info,
+        unit_state: fake_unit_state,
+    }
+}
+
+
+fn render(draw: impl FnOnce(&mut DisplayImage) -> Result<(), Box<dyn std::error::Error>>) -> DisplayImage {
+    let mut img = EPD_2IN7_V2.image_white_landscape();
+    draw(&mut img).expect("The view should draw");
+    img
+}
+
+
+fn compare(name: &str, img: &DisplayImage) {
+    let golden_path = root().join("tests/golden").join(format!("{}.png", name));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        img.save(&golden_path).unwrap();
+        return;
+    }
+
+    assert!(golden_path.exists(), "No golden image for '{}', run with UPDATE_GOLDEN=1 to write it", name);
+
+    let golden = image::open(&golden_path).unwrap().to_rgb8();
+    assert_eq!(golden.dimensions(), img.dimensions(), "Size of '{}' differs from the golden image", name);
+
+    let mut diff = GrayImage::from_pixel(img.width(), img.height(), Luma([255]));
+    let mut changed = 0;
+
+    for (x, y, Rgb(a)) in img.enumerate_pixels() {
+        let Rgb(b) = golden.get_pixel(x, y);
+        if a.iter().zip(b).any(|(a, b)| a.abs_diff(*b) > PIXEL_TOLERANCE) {
+            diff.put_pixel(x, y, Luma([0]));
+            changed += 1;
+        }
+    }
+
+    let ratio = changed as f32 / (img.width() * img.height()) as f32;
+    if ratio > IMAGE_TOLERANCE {
+        let diff_dir = root().join("target/golden-diff");
+        std::fs::create_dir_all(&diff_dir).unwrap();
+        let diff_path = diff_dir.join(format!("{}.png", name));
+        diff.save(&diff_path).unwrap();
+        img.save(diff_dir.join(format!("{}.actual.png", name))).unwrap();
+
+        panic!(
+            "'{}' differs from the golden image in {} pixels ({:.2}%), see {}",
+            name, changed, ratio * 100.0, diff_path.display(),
+        );
+    }
+}
+
+
+fn gradient() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(120, 80, |x, y| {
+        let v = ((x + y) * 255 / 200) as u8;
+        Rgb([v, v, v])
+    }))
+}
+
+
+#[test]
+fn clear() {
+    compare("clear", &render(|_| Ok(())));
+}
+
+
+#[test]
+fn debug() {
+    for (name, pattern) in [
+        ("debug-axes", Pattern::Axes),
+        ("debug-greys", Pattern::Greys),
+        ("debug-gradient", Pattern::Gradient),
+        ("debug-checker", Pattern::Checker),
+    ] {
+        compare(name, &render(|img| super::debug::debug(img, pattern)));
+    }
+}
+
+
+#[test]
+fn tasks() {
+    let ctx = context();
+    compare("tasks", &render(|img| super::tasks::tasks(img, &ctx, &TASKS)));
+
+    let properties = ["focus".to_string(), "mood".to_string(), "missing".to_string()];
+    compare("tasks-properties", &render(|img| super::tasks::tasks(img, &ctx, &Options { properties: &properties, ..TASKS })));
+
+    let headings = ["Plan".to_string(), "Habits".to_string(), "Missing".to_string()];
+    compare("tasks-progress", &render(|img| super::tasks::tasks(img, &ctx, &Options { headings: &headings, ..TASKS })));
+
+    let mut ctx = context();
+    ctx.now = NaiveDate::from_ymd_opt(2026, 11, 2).unwrap().and_hms_opt(9, 0, 0).unwrap();
+    let statuses = [Status::InProgress, Status::Important, Status::Todo, Status::Deferred];
+    compare("tasks-statuses", &render(|img| super::tasks::tasks(img, &ctx, &Options { statuses: &statuses, by_status: true, ..TASKS })));
+
+    let ctx = context();
+    let sections = ["notes".to_string()];
+    compare("tasks-sections", &render(|img| super::tasks::tasks(img, &ctx, &Options { sections: &sections, ..TASKS })));
+}
+
+
+#[test]
+fn network() {
+    let ctx = context();
+    compare("network", &render(|img| super::network::network(img, &ctx)));
+}
+
+
+#[test]
+fn message() {
+    compare("message", &render(|img| super::message::message(img, "Dinner is ready")));
+}
+
+
+#[test]
+fn picture() {
+    let options = super::image::Options { fit: Scaling::Fit, gamma: 1.0, contrast: 1.0, levels: 4, dither: Dither::FloydSteinberg };
+    compare("picture", &render(|img| super::image::picture(img, gradient(), &options)));
+}
+
+
+#[test]
+fn missing_note() {
+    let mut ctx = context();
+    ctx.now = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap().and_hms_opt(9, 0, 0).unwrap();
+
+    let mut img = EPD_2IN7_V2.image_white_landscape();
+    let error = super::tasks::tasks(&mut img, &ctx, &TASKS).unwrap_err();
+    assert_eq!(error.downcast_ref::<AppError>().map(AppError::category), Some("Vault missing"));
+
+    compare("tasks-missing-note", &render(|img| super::message::failure(img, error.as_ref(), ctx.now)));
+}
+
+
+#[test]
+fn clock() {
+    let ctx = context();
+    compare("clock", &render(|img| super::clock::clock(img, &ctx)));
+}
+
+
+#[test]
+fn habits() {
+    let ctx = context();
+    let names = ["water".to_string(), "meditate".to_string(), "exercise".to_string()];
+
+    compare("habits", &render(|img| super::habits::habits(img, &ctx, &names, 28)));
+}
+
+
+#[test]
+fn focus() {
+    let ctx = context();
+    let at = |h, m| ctx.now.date().and_hms_opt(h, m, 0).unwrap();
+
+    let mut state = super::focus::Focus::new(super::focus::open_tasks(&ctx).unwrap(), Duration::minutes(25), Duration::minutes(5));
+    state.next_task();
+
+    // 10:22 to 10:50 with a 3 minute pause, the break starts on its own
+    state.toggle(at(10, 22));
+    state.toggle(at(10, 30));
+    state.toggle(at(10, 33));
+    assert!(state.advance(at(10, 49)).is_none());
+
+    let session = state.advance(at(10, 51)).unwrap();
+    assert_eq!(session.log_line(), "- 10:22–10:50 🍅 Write the report");
+    assert_eq!(state.phase, super::focus::Phase::Break);
+    assert!(state.advance(at(10, 56)).is_none());
+
+    // The next session waits for a start
+    assert_eq!(state.phase, super::focus::Phase::Work);
+    assert!(!state.is_running());
+    state.toggle(at(11, 10));
+
+    compare("focus", &render(|img| super::focus::focus(img, &ctx, &state)));
+}
+
+
+#[test]
+fn widgets() {
+    let ctx = context();
+    let path = root().join("tests/fixtures/data/widgets.json");
+
+    compare("widgets", &render(|img| super::widgets::widgets(img, &ctx, &path)));
+}
+
+
+#[test]
+fn screen() {
+    let ctx = context();
+    let path = root().join("tests/fixtures/screens/dashboard.toml");
+
+    compare("screen", &render(|img| super::screen::screen(img, &ctx, &path, 4)));
+}
+
+
+#[test]
+fn script() {
+    let timeout = std::time::Duration::from_secs(5);
+
+    let options = super::image::Options { fit: Scaling::Fit, gamma: 1.0, contrast: 1.0, levels: 2, dither: Dither::None };
+
+    compare("script", &render(|img| super::script::script(img, "echo 21.5°C", timeout, TextStyle::Big, &options)));
+    compare("script-error", &render(|img| super::script::script(img, "echo 'No route to host' >&2; exit 3", timeout, TextStyle::Normal, &options)));
+
+    let dot = root().join("tests/fixtures/screens/dot.png");
+    let output = super::script::run(&format!("cat '{}'", dot.display()), timeout);
+    assert!(matches!(output, Ok(super::script::Output::Picture(p)) if p.width() == 40));
+
+    let output = super::script::run("sleep 5", std::time::Duration::from_millis(100));
+    assert!(matches!(output, Err(super::script::Failure::TimedOut { .. })));
+
+    // Left running with the output, it's stopped at the deadline too
+    let started = std::time::Instant::now();
+    let output = super::script::run("sleep 5 & echo hi", std::time::Duration::from_millis(100));
+    assert!(matches!(output, Err(super::script::Failure::TimedOut { .. })));
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+}
+
+
+#[test]
+fn calendar() {
+    let ctx = context();
+    compare("calendar", &render(|img| super::calendar::calendar(img, &ctx, &[])));
+
+    let ics = [root().join("tests/fixtures/calendars")];
+    compare("calendar-events", &render(|img| super::calendar::calendar(img, &ctx, &ics)));
+}
+
+
+#[test]
+fn section() {
+    let ctx = context();
+    compare("section", &render(|img| super::section::section(img, &ctx, "Notes")));
+}
+
+
+#[test]
+fn agenda() {
+    let ctx = context();
+    compare("agenda", &render(|img| super::agenda::agenda(img, &ctx, 6, 7)));
+
+    // No note today, only days ahead
+    let mut ctx = context();
+    ctx.now = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap().and_hms_opt(9, 0, 0).unwrap();
+    compare("agenda-ahead", &render(|img| super::agenda::agenda(img, &ctx, 6, 0)));
+}
+
+
+#[test]
+fn month() {
+    let ctx = context();
+    compare("month", &render(|img| super::month::month(img, &ctx)));
+}
+
+
+#[test]
+fn error_badge() {
+    let ctx = context();
+    compare("error-badge", &render(|img| {
+        super::tasks::tasks(img, &ctx, &TASKS)?;
+        super::message::badge(img, ctx.now)
+    }));
+}
+
+
+#[test]
+fn status_bar() {
+    // A vault changed 5 minutes ago
+    let root = std::env::temp_dir().join(format!("pinterface-status-{}", std::process::id()));
+    std::fs::create_dir_all(root.join(".obsidian")).unwrap();
+    let file = std::fs::File::create(root.join("note.md")).unwrap();
+
+    let mut ctx = context();
+    let modified = (ctx.now - Duration::minutes(5)).and_local_timezone(chrono::Local).unwrap();
+    file.set_modified(modified.into()).unwrap();
+    ctx.vault = Vault::new(&root);
+
+    compare("status-bar", &render(|img| {
+        let area = super::status::view_rect(img);
+        let mut view = DisplayImage::from_pixel(area.width(), area.height(), WHITE);
+        super::message::message(&mut view, "Dinner is ready")?;
+        image::imageops::replace(img, &view, 0, 0);
+        super::status::status_bar(img, &ctx, DisplayMode::Fast, &[], ctx.vault.changes().ok().as_ref())
+    }));
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+
+#[test]
+fn status_bar_sync() {
+    let ctx = context();
+    let problems = [Problem::Unit("failed".to_string()), Problem::Conflicts(2)];
+
+    compare("status-bar-sync", &render(|img| {
+        let area = super::status::view_rect(img);
+        let mut view = DisplayImage::from_pixel(area.width(), area.height(), WHITE);
+        super::message::message(&mut view, "Dinner is ready")?;
+        image::imageops::replace(img, &view, 0, 0);
+        super::status::status_bar(img, &ctx, DisplayMode::Fast, &problems, None)
+    }));
+}
+
+
+#[test]
+fn sync_banner() {
+    let ctx = context();
+    let problems = [Problem::Unit("failed".to_string()), Problem::Stale(Duration::days(2)), Problem::Conflicts(1)];
+
+    compare("sync-banner", &render(|img| {
+        super::tasks::tasks(img, &ctx, &TASKS)?;
+        super::sync::banner(img, &problems)
+    }));
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use imageproc::rect::Rect;
+use log::{debug, warn};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{app_error::AppError, data::{self, Data, Source}, driver::DisplayImage, render};
+
+use super::Context;
+
+
+const MARGIN: i32 = 6;
+const LINE_HEIGHT: u32 = 24;
+const LABEL_SIZE: f32 = 16.0;
+const VALUE_SIZE: f32 = 20.0;
+
+/// Shown instead of a value that can't be read
+const MISSING: &str = "?";
+
+
+/// Data sources and the widgets showing them, from a JSON file:
+///
+/// ```json
+/// {
+///   "sources": {"weather": {"path": "weather.json", "max-age": 3600}},
+///   "widgets": [{"type": "number", "label": "Outside", "source": "weather", "value": "current.temp", "unit": "°C"}]
+/// }
+/// ```
+#[derive(Deserialize, Debug)]
+pub struct Config {
+    pub sources: HashMap<String, Source>,
+    pub widgets: Vec<Widget>,
+}
+
+impl Config {
+    /// Read the config, with relative source paths taken from its directory
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let s = std::fs::read_to_string(path).map_err(|e| AppError::Config { path: path.to_path_buf(), message: e.to_string() })?;
+        let mut config: Self = serde_json::from_str(&s).map_err(|e| AppError::Config {
+            path: path.to_path_buf(),
+            message: format!("Wrong widgets: {}", e),
+        })?;
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        for source in config.sources.values_mut() {
+            *source = source.relative_to(dir);
+        }
+
+        Ok(config)
+    }
+}
+
+
+/// A value from a data source
+#[derive(Deserialize, Clone, Debug)]
+pub struct Widget {
+    #[serde(default)]
+    pub label: Option<String>,
+    pub source: String,
+    #[serde(flatten)]
+    pub kind: WidgetKind,
+}
+
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum WidgetKind {
+    /// The value as it is
+    Text {
+        value: String,
+    },
+    /// A symbol picked by the value, e.g. `{"rain": "☔", "clear": "☀"}`
+    Icon {
+        value: String,
+        icons: HashMap<String, String>,
+        #[serde(default)]
+        default: Option<String>,
+    },
+    /// A number rounded to `decimals`, followed by the unit
+    Number {
+        value: String,
+        #[serde(default)]
+        unit: String,
+        #[serde(default)]
+        decimals: Option<usize>,
+    },
+    /// Two numbers as a range, e.g. `8–14°C`
+    MinMax {
+        min: String,
+        max: String,
+        #[serde(default)]
+        unit: String,
+        #[serde(default)]
+        decimals: Option<usize>,
+    },
+}
+
+impl Widget {
+    /// What the widget shows for the data, `None` when the values are not there
+    pub fn text(&self, data: &Data) -> Option<String> {
+        match &self.kind {
+            WidgetKind::Text { value } => data.get(value).map(data::to_text),
+            WidgetKind::Icon { value, icons, default } => {
+                let key = data.get(value).map(data::to_text)?;
+                icons.get(&key).or(default.as_ref()).cloned()
+            },
+            WidgetKind::Number { value, unit, decimals } => {
+                let n = number(data.get(value)?, *decimals)?;
+                Some(format!("{}{}", n, unit))
+            },
+            WidgetKind::MinMax { min, max, unit, decimals } => {
+                let min = number(data.get(min)?, *decimals)?;
+                let max = number(data.get(max)?, *decimals)?;
+                Some(format!("{}–{}{}", min, max, unit))
+            },
+        }
+    }
+
+    /// What the widget shows, with how old the data is if it's stale
+    pub fn show(&self, sources: &HashMap<String, Source>, ctx: &Context) -> (String, Option<String>) {
+        let data = sources.get(&self.source)
+            .ok_or(AppError::Data(format!("No source '{}'", self.source)))
+            .and_then(Source::read);
+
+        match data {
+            Ok(data) => match self.text(&data) {
+                Some(text) => (text, data.stale(ctx.now).map(data::format_age)),
+                None => {
+                    warn!("No value for {:?} in '{}'", self.kind, self.source);
+                    (MISSING.to_string(), None)
+                },
+            },
+            Err(e) => {
+                warn!("Could not read '{}': {}", self.source, e);
+                (MISSING.to_string(), None)
+            },
+        }
+    }
+}
+
+
+fn number(value: &Value, decimals: Option<usize>) -> Option<String> {
+    let n = match value {
+        Value::String(s) => s.trim().parse().ok()?,
+        value => value.as_f64()?,
+    };
+
+    Some(match decimals {
+        Some(decimals) => format!("{:.*}", decimals, n),
+        None => n.to_string(),
+    })
+}
+
+
+/// A line per widget: the label on the left, the value on the right
+pub fn widgets(img: &mut DisplayImage, ctx: &Context, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load(path)?;
+    let width = img.width() - 2 * MARGIN as u32;
+
+    for (i, widget) in config.widgets.iter().enumerate() {
+        let top = MARGIN + (i as u32 * LINE_HEIGHT) as i32;
+        if top as u32 + LINE_HEIGHT > img.height() {
+            warn!("No room for {} more widgets", config.widgets.len() - i);
+            break;
+        }
+
+        let (text, stale) = widget.show(&config.sources, ctx);
+        debug!("{:?}: {} {:?}", widget.label, text, stale);
+
+        if let Some(label) = &widget.label {
+            let rect = Rect::at(MARGIN, top + (VALUE_SIZE - LABEL_SIZE) as i32).of_size(width, LINE_HEIGHT);
+            render::draw_rich_text_sized(img, rect, &[(label, render::TEXT)], LABEL_SIZE)?;
+        }
+
+        let rect = Rect::at(MARGIN, top).of_size(width, LINE_HEIGHT);
+        draw_value(img, rect, &text, stale, VALUE_SIZE)?;
+    }
+
+    Ok(())
+}
+
+
+/// Draw the value on the right of `rect`, values too old to trust or missing stand out
+pub fn draw_value(img: &mut DisplayImage, rect: Rect, text: &str, stale: Option<String>, size: f32) -> Result<(), Box<dyn std::error::Error>> {
+    let age = stale.map(|age| format!(" {}", age));
+    let color = if age.is_some() || text == MISSING { render::TEXT_ACCENT } else { render::TEXT };
+
+    let mut spans = vec![(text, color)];
+    if let Some(age) = &age {
+        spans.push((age, render::TEXT_ACCENT));
+    }
+
+    let value_width = render::text_width(&format!("{}{}", text, age.as_deref().unwrap_or("")), size)?;
+    let left = (rect.width() as i32 - value_width as i32).max(0);
+    let rect = Rect::at(rect.left() + left, rect.top()).of_size(rect.width() - left as u32, rect.height());
+
+    render::draw_rich_text_sized(img, rect, &spans, size)
+}
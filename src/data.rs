@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Duration, Local, NaiveDateTime};
+use lazy_static::lazy_static;
+use log::debug;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::app_error::AppError;
+
+
+lazy_static! {
+    /// Parsed files by path, with the modification time they were read at
+    static ref CACHE: Mutex<HashMap<PathBuf, (SystemTime, Value)>> = Mutex::new(HashMap::new());
+}
+
+
+/// JSON file written by another program, e.g. a cron job fetching the weather
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct Source {
+    pub path: PathBuf,
+    /// Seconds after which the data counts as stale
+    #[serde(default)]
+    pub max_age: Option<i64>,
+    /// Where the time of the data is in the file, the modification time of the file is used otherwise
+    #[serde(default)]
+    pub updated: Option<String>,
+}
+
+
+/// Contents of a source
+#[derive(Debug)]
+pub struct Data {
+    pub value: Value,
+    pub updated: NaiveDateTime,
+    max_age: Option<i64>,
+}
+
+impl Source {
+    /// Read the file, or take it from the cache if it hasn't changed since the last read
+    pub fn read(&self) -> Result<Data, AppError> {
+        let modified = std::fs::metadata(&self.path)?.modified()?;
+
+        let mut cache = CACHE.lock().map_err(|e| AppError::Data(e.to_string()))?;
+
+        let value = match cache.get(&self.path) {
+            Some((read_at, value)) if *read_at == modified => value.clone(),
+            _ => {
+                debug!("Reading {}", self.path.display());
+
+                let s = std::fs::read_to_string(&self.path)?;
+                let value: Value = serde_json::from_str(&s).map_err(|e| AppError::Data(
+                    format!("Wrong JSON in {}: {}", self.path.display(), e)
+                ))?;
+
+                cache.insert(self.path.clone(), (modified, value.clone()));
+                value
+            },
+        };
+
+        let updated = match &self.updated {
+            Some(path) => lookup(&value, path).and_then(timestamp).ok_or(AppError::Data(
+                format!("No time at '{}' in {}", path, self.path.display())
+            ))?,
+            None => DateTime::<Local>::from(modified).naive_local(),
+        };
+
+        Ok(Data { value, updated, max_age: self.max_age })
+    }
+
+    /// The same source with a relative path taken from `dir`
+    pub fn relative_to(&self, dir: &Path) -> Self {
+        Self { path: dir.join(&self.path), ..self.clone() }
+    }
+}
+
+impl Data {
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        lookup(&self.value, path)
+    }
+
+    pub fn age(&self, now: NaiveDateTime) -> Duration {
+        now - self.updated
+    }
+
+    /// How old the data is, if it's older than it should be
+    pub fn stale(&self, now: NaiveDateTime) -> Option<Duration> {
+        let age = self.age(now);
+        self.max_age
+            .filter(|max_age| age > Duration::seconds(*max_age))
+            .map(|_| age)
+    }
+}
+
+
+/// Find a value by a JSON pointer (`/hourly/0/temp`) or a path (`hourly[0].temp`, `hourly.0.temp`)
+pub fn lookup<'v>(value: &'v Value, path: &str) -> Option<&'v Value> {
+    if path.is_empty() || path.starts_with('/') {
+        return value.pointer(path);
+    }
+
+    let pointer: String = path
+        .replace('[', ".")
+        .replace(']', "")
+        .split('.')
+        .filter(|key| !key.is_empty())
+        .map(|key| format!("/{}", key.replace('~', "~0").replace('/', "~1")))
+        .collect();
+
+    value.pointer(&pointer)
+}
+
+
+/// Time from a value: seconds since the epoch, RFC 3339, or local time without a zone
+fn timestamp(value: &Value) -> Option<NaiveDateTime> {
+    if let Some(seconds) = value.as_i64() {
+        return DateTime::from_timestamp(seconds, 0).map(|t| t.with_timezone(&Local).naive_local());
+    }
+
+    let s = value.as_str()?;
+
+    DateTime::parse_from_rfc3339(s).map(|t| t.with_timezone(&Local).naive_local())
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S"))
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
+        .ok()
+}
+
+
+/// Text for a value: strings as they are, everything else as JSON
+pub fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+
+/// Short age for the screen: `45s`, `12m`, `3h`, `2d`
+pub fn format_age(age: Duration) -> String {
+    match age.num_seconds() {
+        s if s < 60 => format!("{}s", s.max(0)),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86_400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86_400),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn paths() {
+        let value = json!({"current": {"temp": 12.5}, "hourly": [{"temp": 11}, {"temp": 10}], "a/b": 1});
+
+        assert_eq!(lookup(&value, "/current/temp"), Some(&json!(12.5)));
+        assert_eq!(lookup(&value, "current.temp"), Some(&json!(12.5)));
+        assert_eq!(lookup(&value, "hourly[1].temp"), Some(&json!(10)));
+        assert_eq!(lookup(&value, "hourly.0.temp"), Some(&json!(11)));
+        assert_eq!(lookup(&value, "a/b"), Some(&json!(1)));
+        assert_eq!(lookup(&value, "/a~1b"), Some(&json!(1)));
+        assert_eq!(lookup(&value, "hourly[2].temp"), None);
+        assert_eq!(lookup(&value, ""), Some(&value));
+    }
+
+    #[test]
+    fn timestamps() {
+        let t = NaiveDateTime::parse_from_str("2026-10-18 09:20:00", "%Y-%m-%d %H:%M:%S").unwrap();
+
+        assert_eq!(timestamp(&json!("2026-10-18T09:20:00")), Some(t));
+        assert_eq!(timestamp(&json!("2026-10-18 09:20:00")), Some(t));
+        assert!(timestamp(&json!("2026-10-18T09:20:00+02:00")).is_some());
+        assert!(timestamp(&json!(1_792_000_000)).is_some());
+        assert_eq!(timestamp(&json!("yesterday")), None);
+    }
+
+    #[test]
+    fn ages() {
+        assert_eq!(format_age(Duration::seconds(45)), "45s");
+        assert_eq!(format_age(Duration::minutes(12)), "12m");
+        assert_eq!(format_age(Duration::hours(3)), "3h");
+        assert_eq!(format_age(Duration::days(2)), "2d");
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::{Duration, Instant};
+
+use log::info;
+use rppal::gpio::{Gpio, InputPin};
+
+use super::DriverError;
+
+
+/// Keys on the side of the HAT, from the top
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+}
+
+
+/// The four keys of the 2.7 inch HAT, pulled up and pressed when low
+pub struct Buttons {
+    keys: Vec<(Button, InputPin)>,
+}
+
+impl Buttons {
+
+    const IN_KEY1: u8 = 5;
+    const IN_KEY2: u8 = 6;
+    const IN_KEY3: u8 = 13;
+    const IN_KEY4: u8 = 19;
+
+    // How often the keys are read
+    const POLL_MS: u64 = 20;
+
+    pub fn new() -> Result<Self, DriverError> {
+        let gpio = Gpio::new()?;
+
+        let keys = [
+            (Button::Key1, Self::IN_KEY1),
+            (Button::Key2, Self::IN_KEY2),
+            (Button::Key3, Self::IN_KEY3),
+            (Button::Key4, Self::IN_KEY4),
+        ];
+
+        let keys = keys.into_iter()
+            .map(|(button, pin)| Ok((button, gpio.get(pin)?.into_input_pullup())))
+            .collect::<Result<_, DriverError>>()?;
+
+        Ok(Self { keys })
+    }
+
+    /// Wait for a key to be pressed and released, or `None` when the time is up
+    pub fn wait(&self, timeout: Duration) -> Option<Button> {
+        let deadline = Instant::now() + timeout;
+
+        while Instant::now() < deadline {
+            if let Some((button, pin)) = self.keys.iter().find(|(_, pin)| pin.is_low()) {
+                // Act on release, so that one press is one action
+                while pin.is_low() {
+                    std::thread::sleep(Duration::from_millis(Self::POLL_MS));
+                }
+
+                info!("{:?} pressed", button);
+                return Some(*button);
+            }
+
+            std::thread::sleep(Duration::from_millis(Self::POLL_MS));
+        }
+
+        None
+    }
+}
@@ -0,0 +1,237 @@
+use std::ops::Range;
+use std::path::Path;
+
+use log::info;
+
+use super::DriverError;
+
+
+/// Waveform in the 159 byte SSD16xx layout
+///
+/// Only the first 153 bytes go to the LUT register (0x32), the tail holds the voltages,
+/// which are written to their own registers:
+///
+/// | Byte | Register | Meaning      |
+/// | ---- | -------- | ------------ |
+/// | 153  | 0x3F     | EOPQ         |
+/// | 154  | 0x03     | VGH          |
+/// | 155  | 0x04     | VSH1         |
+/// | 156  | 0x04     | VSH2         |
+/// | 157  | 0x04     | VSL          |
+/// | 158  | 0x2C     | VCOM         |
+#[derive(Clone, Debug)]
+pub struct Lut {
+    data: Vec<u8>,
+}
+
+impl Lut {
+    pub const LEN: usize = 159;
+
+    pub fn new(data: Vec<u8>) -> Result<Self, DriverError> {
+        let lut = Self { data };
+        lut.validate()?;
+        Ok(lut)
+    }
+
+    /// Load a waveform from a file.
+    ///
+    /// Text files contain hex (`0x1C`) or decimal bytes separated with commas or whitespace,
+    /// `#` and `//` start comments.
+    /// Anything that is not valid UTF-8 is taken as raw bytes.
+    pub fn load(path: &Path) -> Result<Self, DriverError> {
+        info!("Loading waveform from {}", path.display());
+
+        let bytes = std::fs::read(path)?;
+
+        let data = match String::from_utf8(bytes) {
+            Ok(text) => Self::parse(&text)?,
+            Err(e) => e.into_bytes(),
+        };
+
+        Self::new(data)
+    }
+
+    fn parse(text: &str) -> Result<Vec<u8>, DriverError> {
+        let mut data = Vec::new();
+
+        for line in text.lines() {
+            let line = line.split("//").next().unwrap_or_default();
+            let line = line.split('#').next().unwrap_or_default();
+
+            for word in line.split([',', ' ', '\t']).filter(|w| !w.is_empty()) {
+                let byte = match word.strip_prefix("0x").or(word.strip_prefix("0X")) {
+                    Some(hex) => u8::from_str_radix(hex, 16),
+                    None => word.parse(),
+                };
+
+                data.push(byte.map_err(|_| DriverError::WrongInput(format!(
+                    "'{}' is not a byte in the waveform", word,
+                )))?);
+            }
+        }
+
+        Ok(data)
+    }
+
+    fn validate(&self) -> Result<(), DriverError> {
+        if self.data.len() != Self::LEN {
+            return Err(DriverError::WrongInput(format!(
+                "Waveform has to be {} bytes long, got {}", Self::LEN, self.data.len(),
+            )));
+        }
+
+        let check = |name: &str, value: u8, valid: bool| {
+            if valid {
+                Ok(())
+            } else {
+                Err(DriverError::WrongInput(format!("{} of 0x{:02X} is out of range", name, value)))
+            }
+        };
+
+        // Ranges from the SSD1680 specification, 0x00 keeps the power-on default
+        let vsh = |v: u8| v == 0x00 || (0x23..=0x50).contains(&v) || (0x8E..=0xCE).contains(&v);
+
+        check("VGH", self.vgh(), self.vgh() == 0x00 || (0x03..=0x17).contains(&self.vgh()))?;
+        check("VSH1", self.vsh1(), vsh(self.vsh1()))?;
+        check("VSH2", self.vsh2(), vsh(self.vsh2()))?;
+        check("VSL", self.vsl(), self.vsl() == 0x00 || ((0x1A..=0x3A).contains(&self.vsl()) && self.vsl().is_multiple_of(2)))?;
+        check("VCOM", self.vcom(), (0x08..=0x78).contains(&self.vcom()))?;
+
+        Ok(())
+    }
+
+    /// The part for the LUT register, without the voltages
+    pub fn waveform(&self) -> &[u8] {
+        &self.data[0..153]
+    }
+
+    pub fn eopq(&self) -> u8 {
+        self.data[153]
+    }
+
+    pub fn vgh(&self) -> u8 {
+        self.data[154]
+    }
+
+    pub fn vsh1(&self) -> u8 {
+        self.data[155]
+    }
+
+    pub fn vsh2(&self) -> u8 {
+        self.data[156]
+    }
+
+    pub fn vsl(&self) -> u8 {
+        self.data[157]
+    }
+
+    pub fn vcom(&self) -> u8 {
+        self.data[158]
+    }
+}
+
+
+/// Waveforms for a single mode, each for its own range of temperatures in °C
+#[derive(Debug, Default)]
+pub struct LutSet {
+    luts: Vec<(Option<Range<f32>>, Lut)>,
+}
+
+impl LutSet {
+    pub fn push(&mut self, band: Option<Range<f32>>, lut: Lut) {
+        self.luts.push((band, lut));
+    }
+
+    /// Pick the waveform for the temperature.
+    ///
+    /// Waveforms without a band are used when the temperature is unknown or no band matches it.
+    pub fn pick(&self, temperature: Option<f32>) -> Option<&Lut> {
+        let in_band = temperature.and_then(|t| self.luts.iter().find(|(band, _)| {
+            band.as_ref().is_some_and(|band| band.contains(&t))
+        }));
+        let fallback = || self.luts.iter().find(|(band, _)| band.is_none());
+
+        in_band.or_else(fallback).map(|(_, lut)| lut)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A valid waveform: zeros, then EOPQ, VGH, VSH1, VSH2, VSL and VCOM
+    fn data() -> Vec<u8> {
+        let mut data = vec![0; 153];
+        data.extend([0x22, 0x17, 0x41, 0xA8, 0x32, 0x30]);
+        data
+    }
+
+    #[test]
+    fn parse() {
+        let text = "0x20, 0X1c 7\t255 // VCOM\n# Comment, 12\n\n1,2";
+        assert_eq!(Lut::parse(text).unwrap(), [0x20, 0x1C, 7, 255, 1, 2]);
+
+        assert!(Lut::parse("256").is_err());
+        assert!(Lut::parse("0xZZ").is_err());
+    }
+
+    #[test]
+    fn parts() {
+        let lut = Lut::new(data()).unwrap();
+
+        assert_eq!(lut.waveform().len(), 153);
+        assert_eq!(
+            [lut.eopq(), lut.vgh(), lut.vsh1(), lut.vsh2(), lut.vsl(), lut.vcom()],
+            [0x22, 0x17, 0x41, 0xA8, 0x32, 0x30],
+        );
+    }
+
+    #[test]
+    fn validate() {
+        assert!(Lut::new(data()[..153].to_vec()).is_err());
+
+        let wrong = |i: usize, value: u8| {
+            let mut data = data();
+            data[i] = value;
+            Lut::new(data).is_err()
+        };
+
+        assert!(wrong(154, 0x18));
+        assert!(wrong(155, 0x60));
+        assert!(wrong(157, 0x33));
+        assert!(wrong(158, 0x00));
+
+        // Zero keeps the power-on default
+        assert!(!wrong(154, 0x00));
+    }
+
+    #[test]
+    fn pick() {
+        let lut = |eopq: u8| {
+            let mut data = data();
+            data[153] = eopq;
+            Lut::new(data).unwrap()
+        };
+
+        let mut set = LutSet::default();
+        assert!(set.pick(Some(20.0)).is_none());
+
+        set.push(Some(-20.0..10.0), lut(1));
+        set.push(Some(10.0..40.0), lut(2));
+        assert!(set.pick(None).is_none());
+        assert!(set.pick(Some(50.0)).is_none());
+
+        set.push(None, lut(3));
+        set.push(Some(0.0..50.0), lut(4));
+        let picked = |t: Option<f32>| set.pick(t).map(Lut::eopq);
+
+        assert_eq!(picked(Some(-5.0)), Some(1));
+        assert_eq!(picked(Some(10.0)), Some(2));
+        // The first matching band wins
+        assert_eq!(picked(Some(5.0)), Some(1));
+        assert_eq!(picked(Some(45.0)), Some(4));
+        assert_eq!(picked(Some(60.0)), Some(3));
+        assert_eq!(picked(None), Some(3));
+    }
+}
@@ -0,0 +1,827 @@
+use std::ops::Range;
+
+use image::{ImageBuffer, Pixel, Rgb};
+use imageproc::rect::Rect;
+use log::info;
+use rppal::gpio::{Gpio, InputPin, OutputPin, Level::*};
+use rppal::spi::{self, Spi, Bus, SlaveSelect};
+
+use crate::args::DisplayMode;
+use crate::util::*;
+
+pub mod buttons;
+pub mod lut;
+pub mod panel;
+
+use lut::{Lut, LutSet};
+use panel::{Controller, Panel};
+
+
+#[derive(Debug)]
+pub enum DriverError {
+    Gpio(rppal::gpio::Error),
+    Spi(rppal::spi::Error),
+    Io(std::io::Error),
+    WrongInput(String),
+}
+
+impl std::fmt::Display for DriverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gpio(e) => write!(f, "GPIO: {}", e),
+            Self::Spi(e) => write!(f, "SPI: {}", e),
+            Self::Io(e) => write!(f, "IO: {}", e),
+            Self::WrongInput(e) => write!(f, "Wrong input: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DriverError {}
+
+impl From<rppal::gpio::Error> for DriverError {
+    fn from(value: rppal::gpio::Error) -> Self {
+        Self::Gpio(value)
+    }
+}
+
+impl From<rppal::spi::Error> for DriverError {
+    fn from(value: rppal::spi::Error) -> Self {
+        Self::Spi(value)
+    }
+}
+
+impl From<std::io::Error> for DriverError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+
+pub type DisplayImagePixel = Rgb<u8>;
+pub type DisplayImage = ImageBuffer<DisplayImagePixel, Vec<u8>>;
+
+pub const BLACK: DisplayImagePixel = Rgb([u8::MIN, u8::MIN, u8::MIN]);
+pub const WHITE: DisplayImagePixel = Rgb([u8::MAX, u8::MAX, u8::MAX]);
+/// Drawn with the second ink on three-colour panels, and as black elsewhere
+pub const ACCENT: DisplayImagePixel = Rgb([u8::MAX, u8::MIN, u8::MIN]);
+
+
+/// Brightness of the pixel as it would be shown by a panel without colours
+fn luma(pixel: &DisplayImagePixel) -> u8 {
+    pixel.to_luma().0[0]
+}
+
+
+/// Whether the pixel should be drawn with the accent ink
+pub fn is_accent(pixel: &DisplayImagePixel) -> bool {
+    let [r, g, b] = pixel.0;
+    r > u8::MAX / 2 && g <= u8::MAX / 2 && b <= u8::MAX / 2
+}
+
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+enum ColorGreyscale {
+    Black = 0b00,
+    Dark  = 0b01,
+    Light = 0b10,
+    White = 0b11,
+}
+
+impl ColorGreyscale {
+    const B_FROM: u8 = 0;
+    const B_TO: u8   = Self::B_FROM + u8::MAX / 4;
+
+    const D_FROM: u8 = Self::B_TO + 1;
+    const D_TO: u8   = Self::D_FROM + u8::MAX / 4;
+
+    const L_FROM: u8 = Self::D_TO + 1;
+    const L_TO: u8   = Self::L_FROM + u8::MAX / 4;
+
+    const W_FROM: u8 = Self::L_TO + 1;
+    const W_TO: u8   = Self::W_FROM + u8::MAX / 4;
+
+    pub fn new(color: &DisplayImagePixel) -> Self {
+        match luma(color) {
+            Self::B_FROM..=Self::B_TO => Self::Black,
+            Self::D_FROM..=Self::D_TO => Self::Dark,
+            Self::L_FROM..=Self::L_TO => Self::Light,
+            Self::W_FROM..=Self::W_TO => Self::White,
+        }
+    }
+
+    pub fn bit_0011(&self) -> bool {
+        let num = *self as u8;
+        num & 0b10 > 0
+    }
+
+    pub fn bit_0101(&self) -> bool {
+        let num = *self as u8;
+        num & 0b01 > 0
+    }
+}
+
+
+/// What the panel shows for a pixel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ink {
+    Black,
+    Dark,
+    Light,
+    White,
+    Accent,
+}
+
+impl Ink {
+    pub fn new(panel: &Panel, mode: DisplayMode, pixel: &DisplayImagePixel) -> Self {
+        // Without the second ink, accent pixels are as good as black
+        if panel.accent && is_accent(pixel) {
+            return Self::Accent;
+        }
+
+        match mode {
+            DisplayMode::Grey => match ColorGreyscale::new(pixel) {
+                ColorGreyscale::Black => Self::Black,
+                ColorGreyscale::Dark => Self::Dark,
+                ColorGreyscale::Light => Self::Light,
+                ColorGreyscale::White => Self::White,
+            },
+            DisplayMode::Full | DisplayMode::Fast => if luma(pixel) <= u8::MAX / 2 {
+                Self::Black
+            } else {
+                Self::White
+            },
+        }
+    }
+}
+
+
+pub struct Display {
+    // Output: reset the display
+    rst: OutputPin,
+    // Output: select whether SPI sends commands or data
+    dc: OutputPin,
+    // Output: power?
+    pwr: OutputPin,
+
+    // Input: whether the display is busy
+    busy: InputPin,
+
+    // SPI interface
+    spi: Spi,
+
+    // What is connected to the HAT
+    panel: &'static Panel,
+
+    // Waveforms to use instead of the built-in ones
+    lut_fast: LutSet,
+    lut_grey: LutSet,
+
+    // Temperature in °C to use instead of the internal sensor
+    temperature: Option<f32>,
+}
+
+
+/// Waveshare e-Paper display HAT
+///
+/// The pins and the SPI setup are the same for all the HATs,
+/// the differences between the panels are described by `Panel`.
+///
+/// LUT stands for look up table.
+/// It stores the Waveform which defines the relation between greyscale, voltage and temperature.
+impl Display {
+
+    const OUT_RST: u8 = 17;
+    const OUT_DC: u8 = 25;
+    const OUT_PWR: u8 = 18;
+
+    const IN_BUSY: u8 = 24;
+
+    // SPI bus to use
+    const SPI_BUS: Bus = Bus::Spi0;
+    // SPI device to use
+    const SPI_DEV: SlaveSelect = SlaveSelect::Ss0;
+    // SPI clock speed in Hz to use
+    const SPI_CLOCK_HZ: u32 = 4000000;
+    // SPI mode to use
+    const SPI_MODE: spi::Mode = spi::Mode::Mode0;
+
+
+    pub fn new(panel: &'static Panel) -> Result<Self, DriverError> {
+        info!("Using the {} panel", panel.name);
+
+        let gpio = Gpio::new()?;
+
+        let rst = gpio.get(Self::OUT_RST)?.into_output();
+        let dc = gpio.get(Self::OUT_DC)?.into_output();
+        let pwr = gpio.get(Self::OUT_PWR)?.into_output();
+
+        let busy = gpio.get(Self::IN_BUSY)?.into_input();
+
+        let spi = Spi::new(
+            Self::SPI_BUS,
+            Self::SPI_DEV,
+            Self::SPI_CLOCK_HZ,
+            Self::SPI_MODE,
+        )?;
+
+        Ok(Self {
+            rst,
+            dc,
+            pwr,
+            busy,
+            spi,
+            panel,
+            lut_fast: LutSet::default(),
+            lut_grey: LutSet::default(),
+            temperature: None,
+        })
+    }
+
+    pub fn panel(&self) -> &'static Panel {
+        self.panel
+    }
+
+    /// Use a custom waveform for the mode instead of the one from OTP or the panel description.
+    ///
+    /// With a band, the waveform is only used when the temperature falls into it.
+    pub fn add_lut(&mut self, mode: DisplayMode, band: Option<Range<f32>>, lut: Lut) -> Result<(), DriverError> {
+        if self.panel.controller != Controller::Ssd16xx || !self.panel.supports(mode) {
+            return Err(DriverError::WrongInput(format!(
+                "The {} panel can't use a custom waveform in the {:?} mode", self.panel.name, mode,
+            )));
+        }
+
+        match mode {
+            DisplayMode::Fast => self.lut_fast.push(band, lut),
+            DisplayMode::Grey => self.lut_grey.push(band, lut),
+            DisplayMode::Full => return Err(DriverError::WrongInput(
+                "The Full mode always uses the waveform from OTP".to_string(),
+            )),
+        }
+
+        Ok(())
+    }
+
+    /// Use the temperature in °C for the following refreshes, or the internal sensor if `None`
+    pub fn set_temperature(&mut self, temperature: Option<f32>) {
+        if let Some(t) = temperature {
+            info!("Using the temperature of {:.1}°C", t);
+        }
+        self.temperature = temperature;
+    }
+
+
+    fn reset(&mut self) {
+        info!("reset");
+
+        self.rst.write(High);
+        sleep_ms(200);
+        self.rst.write(Low);
+        sleep_ms(2);
+        self.rst.write(High);
+        sleep_ms(200);
+    }
+
+    fn send_command(&mut self, command: &[u8]) -> Result<(), DriverError> {
+        self.dc.write(Low);
+        self.spi.write(command)?;
+        Ok(())
+    }
+
+    fn send_data(&mut self, data: &[u8]) -> Result<(), DriverError> {
+        self.dc.write(High);
+        self.spi.write(data)?;
+        Ok(())
+    }
+
+    fn wait_not_busy(&mut self) -> Result<(), DriverError> {
+        match self.panel.controller {
+            Controller::Ssd16xx => {
+                while self.busy.read() == High {
+                    sleep_ms(20);
+                }
+            },
+            Controller::Uc8179 => {
+                // Get status
+                self.send_command(&[0x71])?;
+                while self.busy.read() == Low {
+                    sleep_ms(20);
+                    self.send_command(&[0x71])?;
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    fn show(&mut self, mode: DisplayMode) -> Result<(), DriverError>  {
+        info!("{} show", mode);
+
+        match self.panel.controller {
+            Controller::Ssd16xx => {
+                // Display update control
+                self.send_command(&[0x22])?;
+                match (mode, self.temperature) {
+                    // Load temperature value, Load LUT, Display with mode 1
+                    (DisplayMode::Full, None) => self.send_data(&[0xF7])?,
+                    // Load LUT for the temperature written before, Display with mode 1
+                    (DisplayMode::Full, Some(_)) => self.send_data(&[0xD7])?,
+                    // Display with mode 1
+                    (DisplayMode::Fast | DisplayMode::Grey, _) => self.send_data(&[0xC7])?,
+                }
+
+                // Execute the selected update sequence
+                self.send_command(&[0x20])?;
+            },
+            Controller::Uc8179 => {
+                // Display refresh
+                self.send_command(&[0x12])?;
+                sleep_ms(100);
+            },
+        }
+
+        self.wait_not_busy()?;
+
+        Ok(())
+    }
+
+
+    pub fn init(&mut self, mode: DisplayMode) -> Result<(), DriverError> {
+        info!("{} init", mode);
+
+        self.panel.check_mode(mode)?;
+
+        self.pwr.write(High);
+
+        self.reset();
+        self.wait_not_busy()?;
+
+        match self.panel.controller {
+            Controller::Ssd16xx => self.init_ssd16xx(mode),
+            Controller::Uc8179 => self.init_uc8179(),
+        }
+    }
+
+    fn init_ssd16xx(&mut self, mode: DisplayMode) -> Result<(), DriverError> {
+        // SWRESET
+        self.send_command(&[0x12])?;
+        self.wait_not_busy()?;
+
+        // Custom waveform to load after the RAM is set up
+        let lut = match mode {
+            DisplayMode::Full => None,
+            DisplayMode::Fast => self.lut_fast.pick(self.temperature).cloned(),
+            DisplayMode::Grey => match self.lut_grey.pick(self.temperature) {
+                Some(lut) => Some(lut.clone()),
+                None => {
+                    let lut = self.panel.lut_grey.ok_or(DriverError::WrongInput(format!(
+                        "The {} panel has no greyscale waveform", self.panel.name,
+                    )))?;
+                    Some(Lut::new(lut.to_vec())?)
+                },
+            },
+        };
+
+        if let Some(t) = self.temperature {
+            // Write temperature register
+            self.send_command(&[0x1A])?;
+            self.send_data(&Self::temperature_ssd16xx(t))?;
+        }
+
+        if let (DisplayMode::Fast, None, Some(_)) = (mode, &lut, self.temperature) {
+            // Display update control
+            self.send_command(&[0x22])?;
+            // Load LUT for the temperature written before (display mode 1)
+            self.send_data(&[0x91])?;
+
+            // Execute the selected update sequence
+            self.send_command(&[0x20])?;
+            self.wait_not_busy()?;
+        }
+
+        if let (DisplayMode::Fast, None, None) = (mode, &lut, self.temperature) {
+            // Select temperature sensor
+            self.send_command(&[0x18])?;
+            // Internal sensor
+            self.send_data(&[0x80])?;
+
+            // Display update control
+            self.send_command(&[0x22])?;
+            // Load temperature value, Load LUT (display mode 1)
+            self.send_data(&[0xB1])?;
+
+            // Execute the selected update sequence
+            self.send_command(&[0x20])?;
+            self.wait_not_busy()?;
+        }
+
+        if lut.is_some() {
+            // Set analog block control
+            self.send_command(&[0x74])?;
+            self.send_data(&[0x54])?;
+            // Set digital block control
+            self.send_command(&[0x7E])?;
+            self.send_data(&[0x3B])?;
+        }
+
+        // Driver output control: number of gates
+        let [y_byte_1, y_byte_2] = (self.panel.height as u16 - 1).to_le_bytes();
+        self.send_command(&[0x01])?;
+        self.send_data(&[y_byte_1, y_byte_2, 0x00])?;
+
+        // Data entry mode
+        self.send_command(&[0x11])?;
+        // Y increment, X increment, counter updated in X direction
+        self.send_data(&[0b0000_0011])?;
+
+        // Set RAM X address start/end position (in bytes)
+        let (width, ..) = self.panel.size_bytes();
+        self.send_command(&[0x44])?;
+        self.send_data(&[0x00, width as u8 - 1])?;
+
+        // Set RAM Y address start/end position
+        self.send_command(&[0x45])?;
+        self.send_data(&[0x00, 0x00, y_byte_1, y_byte_2])?;
+
+        if let Some(lut) = lut {
+            self.load_lut(&lut)?;
+        }
+
+        Ok(())
+    }
+
+    /// SSD16xx temperature register format: 12 bit two's complement in 1/16 °C, left aligned
+    fn temperature_ssd16xx(t: f32) -> [u8; 2] {
+        let raw = (t * 16.0).round().clamp(-2048.0, 2047.0) as i16;
+        (raw << 4).to_be_bytes()
+    }
+
+    fn load_lut(&mut self, lut: &Lut) -> Result<(), DriverError> {
+        // Don't draw border
+        self.send_command(&[0x3C])?;
+        self.send_data(&[0x00])?;
+
+        // VCOM Voltage
+        self.send_command(&[0x2C])?;
+        self.send_data(&[lut.vcom()])?;
+
+        // EOPQ
+        self.send_command(&[0x3F])?;
+        self.send_data(&[lut.eopq()])?;
+
+        // Gate driving voltage
+        self.send_command(&[0x03])?;
+        self.send_data(&[lut.vgh()])?;
+
+        // Source driving voltage
+        self.send_command(&[0x04])?;
+        self.send_data(&[lut.vsh1(), lut.vsh2(), lut.vsl()])?;
+
+        // LUT, without the voltages written above
+        self.send_command(&[0x32])?;
+        self.send_data(lut.waveform())?;
+
+        self.wait_not_busy()?;
+
+        Ok(())
+    }
+
+    fn init_uc8179(&mut self) -> Result<(), DriverError> {
+        // Power setting: VGH=20V, VGL=-20V, VDH=15V, VDL=-15V
+        self.send_command(&[0x01])?;
+        self.send_data(&[0x07, 0x07, 0x3F, 0x3F])?;
+
+        // Booster soft start
+        self.send_command(&[0x06])?;
+        self.send_data(&[0x17, 0x17, 0x28, 0x17])?;
+
+        // Power on
+        self.send_command(&[0x04])?;
+        sleep_ms(100);
+        self.wait_not_busy()?;
+
+        // Panel setting: KW or KWR mode, LUT from OTP
+        self.send_command(&[0x00])?;
+        self.send_data(&[if self.panel.accent { 0x0F } else { 0x1F }])?;
+
+        // Resolution setting
+        let [w_byte_1, w_byte_2] = (self.panel.width as u16).to_be_bytes();
+        let [h_byte_1, h_byte_2] = (self.panel.height as u16).to_be_bytes();
+        self.send_command(&[0x61])?;
+        self.send_data(&[w_byte_1, w_byte_2, h_byte_1, h_byte_2])?;
+
+        // Dual SPI off
+        self.send_command(&[0x15])?;
+        self.send_data(&[0x00])?;
+
+        // VCOM and data interval setting
+        self.send_command(&[0x50])?;
+        self.send_data(&[0x10, 0x07])?;
+
+        // TCON setting
+        self.send_command(&[0x60])?;
+        self.send_data(&[0x22])?;
+
+        if let Some(t) = self.temperature {
+            // Cascade setting: use the temperature value below instead of the sensor
+            self.send_command(&[0xE0])?;
+            self.send_data(&[0x02])?;
+
+            // Force temperature, whole degrees
+            self.send_command(&[0xE5])?;
+            self.send_data(&[(t.round() as i8) as u8])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn clear(&mut self, mode: DisplayMode) -> Result<(), DriverError> {
+        info!("{} clear", mode);
+        self.display(self.panel.image_white_v(), mode)?;
+        Ok(())
+    }
+
+    pub fn sleep(&mut self) -> Result<(), DriverError> {
+        info!("sleep");
+
+        match self.panel.controller {
+            Controller::Ssd16xx => {
+                self.send_command(&[0x10])?;
+                self.send_data(&[0x01])?;
+            },
+            Controller::Uc8179 => {
+                // Power off
+                self.send_command(&[0x02])?;
+                self.wait_not_busy()?;
+
+                // Deep sleep
+                self.send_command(&[0x07])?;
+                self.send_data(&[0xA5])?;
+            },
+        }
+
+        sleep_ms(2000);
+
+        self.rst.write(Low);
+        self.dc.write(Low);
+        self.pwr.write(Low);
+
+        Ok(())
+    }
+
+    /// Move the image into a RAM plane, setting bits for pixels for which `bit` returns `true`.
+    ///
+    /// Note: how images are moved into a buffer.
+    ///
+    /// When the image is vertical, it is transferred byte to bit as is.
+    /// The default orientation is such that the flexible connector of the screen is on the bottom.
+    /// - (0, 0) of the image corresponds to (0, 0) of the screen
+    ///
+    /// When the image is horizontal, a transformation is necessary.
+    /// The orientation is such that the flexible connector of the screen is on the left.
+    /// Therefore:
+    /// - (0, 0)           -> (0, ScreenH-1)
+    /// - (ImgW-1, 0)      -> (0, 0)
+    /// - (0, ImgH-1)      -> (ScreenW-1, ScreenH-1)
+    /// - (ImgW-1, ImgH-1) -> (ScreenW-1, 0)
+    ///
+    /// TODO: figure out how to do this using memory addressing settings
+    fn pack(&self, img: &DisplayImage, horizontal: bool, bit: impl Fn(&DisplayImagePixel) -> bool) -> Vec<u8> {
+        let (width, ..) = self.panel.size_bytes();
+        let mut buffer = vec![0b00000000; width * self.panel.height as usize];
+
+        for (x, y, pixel) in img.enumerate_pixels() {
+            let (x, y) = if horizontal {
+                (y as usize, (self.panel.height - x - 1) as usize)
+            } else {
+                (x as usize, y as usize)
+            };
+
+            let mask = 0b1000_0000 >> (x % 8);
+            buffer[x / 8 + y * width] |= if bit(pixel) { mask } else { 0 };
+        }
+
+        buffer
+    }
+
+    /// Draw the whole image with a refresh of `mode`.
+    ///
+    /// SSD16xx panels without an accent colour also get the image in the RAM at 0x26, which the controller
+    /// compares the next image with, so that `display_partial` can follow.
+    pub fn display(&mut self, img: DisplayImage, mode: DisplayMode) -> Result<(), DriverError> {
+        info!("{} display", mode);
+
+        let horizontal = self.panel.is_horizontal(&img).ok_or(DriverError::WrongInput(format!(
+            "Image dimensions do not match screen size. Image is {}x{}. Screen is {}, {}",
+            img.width(), img.height(),
+            self.panel.width, self.panel.height,
+        )))?;
+
+        if let DisplayMode::Grey = mode {
+            return self.display_greyscale(img, horizontal);
+        }
+
+        let (panel, accent) = (self.panel, self.panel.accent);
+        let black = |pixel: &DisplayImagePixel| Ink::new(panel, mode, pixel) == Ink::Black;
+        let red = |pixel: &DisplayImagePixel| Ink::new(panel, mode, pixel) == Ink::Accent;
+
+        match (self.panel.controller, accent) {
+            (Controller::Ssd16xx, _) => {
+                let buffer = self.pack(&img, horizontal, |pixel| !black(pixel));
+
+                // Set RAM X and Y address counters to 0
+                self.send_command(&[0x4E])?;
+                self.send_data(&[0x00])?;
+                self.send_command(&[0x4F])?;
+                self.send_data(&[0x00, 0x00])?;
+
+                self.send_command(&[0x24])?;
+                self.send_data(buffer.as_slice())?;
+
+                if accent {
+                    let buffer = self.pack(&img, horizontal, red);
+
+                    // Red RAM
+                    self.send_command(&[0x26])?;
+                    self.send_data(buffer.as_slice())?;
+                } else {
+                    // Previous image for partial updates
+                    self.send_command(&[0x26])?;
+                    self.send_data(buffer.as_slice())?;
+                }
+            },
+            (Controller::Uc8179, false) => {
+                let buffer = self.pack(&img, horizontal, black);
+
+                // Old data
+                self.send_command(&[0x10])?;
+                self.send_data(vec![0x00; buffer.len()].as_slice())?;
+
+                // New data
+                self.send_command(&[0x13])?;
+                self.send_data(buffer.as_slice())?;
+            },
+            (Controller::Uc8179, true) => {
+                let buffer = self.pack(&img, horizontal, |pixel| !black(pixel));
+
+                // Black and white data
+                self.send_command(&[0x10])?;
+                self.send_data(buffer.as_slice())?;
+
+                let buffer = self.pack(&img, horizontal, red);
+
+                // Red data
+                self.send_command(&[0x13])?;
+                self.send_data(buffer.as_slice())?;
+            },
+        }
+
+        self.show(mode)?;
+
+        Ok(())
+    }
+
+    /// Update only the `region` of the image, leaving the rest of the panel alone.
+    ///
+    /// The panel RAM has to hold the previous image, as left by `display` or an earlier partial update.
+    /// `display_greyscale` writes the image in black and white back to the RAM after its refresh for this.
+    pub fn display_partial(&mut self, img: DisplayImage, region: Rect) -> Result<(), DriverError> {
+        info!("Partial display of {:?}", region);
+
+        if !self.panel.supports_partial() {
+            return Err(DriverError::WrongInput(format!(
+                "The {} panel does not support partial updates", self.panel.name,
+            )));
+        }
+
+        let horizontal = self.panel.is_horizontal(&img).ok_or(DriverError::WrongInput(format!(
+            "Image dimensions do not match screen size. Image is {}x{}. Screen is {}, {}",
+            img.width(), img.height(),
+            self.panel.width, self.panel.height,
+        )))?;
+
+        // The region in panel coordinates, see `pack`
+        let (x, y, w, h) = if horizontal {
+            let x = self.panel.height as i32 - region.right() - 1;
+            (region.top(), x, region.height(), region.width())
+        } else {
+            (region.left(), region.top(), region.width(), region.height())
+        };
+
+        // Whole bytes horizontally
+        let (width, ..) = self.panel.size_bytes();
+        let x_bytes = (x.max(0) as usize / 8)..((x.max(0) as usize + w as usize).div_ceil(8).min(width));
+        let rows = (y.max(0) as usize)..((y.max(0) as usize + h as usize).min(self.panel.height as usize));
+
+        // Nothing of the region is on the panel
+        if rows.is_empty() || x_bytes.is_empty() {
+            return Ok(());
+        }
+
+        let panel = self.panel;
+        let buffer = self.pack(&img, horizontal, |pixel| Ink::new(panel, DisplayMode::Fast, pixel) != Ink::Black);
+        let window: Vec<u8> = rows.clone()
+            .flat_map(|row| buffer[row * width + x_bytes.start..row * width + x_bytes.end].iter().copied())
+            .collect();
+
+        self.init(DisplayMode::Full)?;
+
+        // Border waveform: keep it as it is
+        self.send_command(&[0x3C])?;
+        self.send_data(&[0x80])?;
+
+        let [y_start_1, y_start_2] = (rows.start as u16).to_le_bytes();
+        let [y_end_1, y_end_2] = (rows.end as u16 - 1).to_le_bytes();
+
+        // New image to 0x24, then the same to 0x26 as the previous image for the next update
+        for ram in [0x24, 0x26] {
+            // Set RAM X and Y address start/end position
+            self.send_command(&[0x44])?;
+            self.send_data(&[x_bytes.start as u8, x_bytes.end as u8 - 1])?;
+            self.send_command(&[0x45])?;
+            self.send_data(&[y_start_1, y_start_2, y_end_1, y_end_2])?;
+
+            // Set RAM X and Y address counters to the start of the window
+            self.send_command(&[0x4E])?;
+            self.send_data(&[x_bytes.start as u8])?;
+            self.send_command(&[0x4F])?;
+            self.send_data(&[y_start_1, y_start_2])?;
+
+            self.send_command(&[ram])?;
+            self.send_data(window.as_slice())?;
+
+            if ram == 0x24 {
+                // Display update control
+                self.send_command(&[0x22])?;
+                match self.temperature {
+                    // Load temperature value, Load LUT, Display with mode 2
+                    None => self.send_data(&[0xFF])?,
+                    // Load LUT for the temperature written before, Display with mode 2
+                    Some(_) => self.send_data(&[0xDF])?,
+                }
+
+                // Execute the selected update sequence
+                self.send_command(&[0x20])?;
+                self.wait_not_busy()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn display_greyscale(&mut self, img: DisplayImage, horizontal: bool) -> Result<(), DriverError> {
+        // The planes store the inverted bits of the colour
+        let buffer_0011 = self.pack(&img, horizontal, |pixel| !ColorGreyscale::new(pixel).bit_0011());
+        let buffer_0101 = self.pack(&img, horizontal, |pixel| !ColorGreyscale::new(pixel).bit_0101());
+
+        // Set RAM X and Y address counters to 0
+        self.send_command(&[0x4E])?;
+        self.send_data(&[0x00])?;
+        self.send_command(&[0x4F])?;
+        self.send_data(&[0x00, 0x00])?;
+
+        self.send_command(&[0x24])?;
+        self.send_data(buffer_0101.as_slice())?;
+
+        self.send_command(&[0x26])?;
+        self.send_data(buffer_0011.as_slice())?;
+
+        self.show(DisplayMode::Grey)?;
+
+        // The RAM holds bit planes now, partial updates need the image itself as the previous one.
+        // Greys are as good as black or white to them and stay on the panel where the image doesn't change.
+        let panel = self.panel;
+        let buffer = self.pack(&img, horizontal, |pixel| Ink::new(panel, DisplayMode::Fast, pixel) != Ink::Black);
+
+        for ram in [0x24, 0x26] {
+            // Set RAM X and Y address counters to 0
+            self.send_command(&[0x4E])?;
+            self.send_data(&[0x00])?;
+            self.send_command(&[0x4F])?;
+            self.send_data(&[0x00, 0x00])?;
+
+            self.send_command(&[ram])?;
+            self.send_data(buffer.as_slice())?;
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temperatures() {
+        assert_eq!(Display::temperature_ssd16xx(25.0), [0x19, 0x00]);
+        assert_eq!(Display::temperature_ssd16xx(0.5), [0x00, 0x80]);
+        assert_eq!(Display::temperature_ssd16xx(-10.0), [0xF6, 0x00]);
+
+        // Out of the range of the register
+        assert_eq!(Display::temperature_ssd16xx(200.0), [0x7F, 0xF0]);
+        assert_eq!(Display::temperature_ssd16xx(-300.0), [0x80, 0x00]);
+    }
+}
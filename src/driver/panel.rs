@@ -0,0 +1,249 @@
+use crate::args::{DisplayMode, PanelKind};
+
+use super::{DisplayImage, DriverError, WHITE};
+
+
+/// Command set spoken by the display controller
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Controller {
+    /// Solomon Systech SSD1680 / SSD1683 and compatibles.
+    ///
+    /// BUSY is high while busy, black and white data goes to RAM 0x24, red data to 0x26.
+    Ssd16xx,
+    /// UltraChip UC8179 (GD7965).
+    ///
+    /// BUSY is low while busy, new data goes to 0x13 with 1 meaning black.
+    /// In the black, white and red mode 0x10 takes the black and white data and 0x13 takes red.
+    Uc8179,
+}
+
+
+/// Static description of an e-Paper panel
+#[derive(Debug)]
+pub struct Panel {
+    pub name: &'static str,
+
+    /// Width in pixels when the flexible connector is on the bottom
+    pub width: u32,
+    /// Height in pixels when the flexible connector is on the bottom
+    pub height: u32,
+
+    pub controller: Controller,
+
+    /// Display modes the panel can be driven in
+    pub modes: &'static [DisplayMode],
+
+    /// Whether the panel has a second (red or yellow) ink, stored in its own RAM plane
+    pub accent: bool,
+
+    /// Waveform used for `DisplayMode::Grey`, in the 159 byte SSD16xx layout
+    pub lut_grey: Option<&'static [u8]>,
+}
+
+
+/// Waveshare 2.13 inch e-Paper V4
+///
+/// Documentation: https://www.waveshare.com/wiki/2.13inch_e-Paper_HAT_Manual
+pub static EPD_2IN13_V4: Panel = Panel {
+    name: "2.13\" V4",
+    width: 122,
+    height: 250,
+    controller: Controller::Ssd16xx,
+    modes: &[DisplayMode::Full, DisplayMode::Fast],
+    accent: false,
+    lut_grey: None,
+};
+
+/// Waveshare 2.7 inch e-Paper V2
+///
+/// Documentation: https://www.waveshare.com/wiki/2.7inch_e-Paper_HAT_Manual
+/// Specification: https://files.waveshare.com/upload/b/ba/2.7inch_e-Paper_V2_Specification.pdf
+pub static EPD_2IN7_V2: Panel = Panel {
+    name: "2.7\" V2",
+    width: 176,
+    height: 264,
+    controller: Controller::Ssd16xx,
+    modes: &[DisplayMode::Full, DisplayMode::Fast, DisplayMode::Grey],
+    accent: false,
+    lut_grey: Some(LUT_2IN7_V2_GREY),
+};
+
+/// Waveshare 2.9 inch e-Paper V2
+///
+/// Documentation: https://www.waveshare.com/wiki/2.9inch_e-Paper_Module_Manual
+pub static EPD_2IN9_V2: Panel = Panel {
+    name: "2.9\" V2",
+    width: 128,
+    height: 296,
+    controller: Controller::Ssd16xx,
+    modes: &[DisplayMode::Full, DisplayMode::Fast],
+    accent: false,
+    lut_grey: None,
+};
+
+/// Waveshare 4.2 inch e-Paper V2
+///
+/// Documentation: https://www.waveshare.com/wiki/4.2inch_e-Paper_Module_Manual
+pub static EPD_4IN2_V2: Panel = Panel {
+    name: "4.2\" V2",
+    width: 400,
+    height: 300,
+    controller: Controller::Ssd16xx,
+    modes: &[DisplayMode::Full, DisplayMode::Fast],
+    accent: false,
+    lut_grey: None,
+};
+
+/// Waveshare 7.5 inch e-Paper V2
+///
+/// Documentation: https://www.waveshare.com/wiki/7.5inch_e-Paper_HAT_Manual
+pub static EPD_7IN5_V2: Panel = Panel {
+    name: "7.5\" V2",
+    width: 800,
+    height: 480,
+    controller: Controller::Uc8179,
+    modes: &[DisplayMode::Full],
+    accent: false,
+    lut_grey: None,
+};
+
+/// Waveshare 2.13 inch e-Paper (B) V4
+///
+/// Documentation: https://www.waveshare.com/wiki/2.13inch_e-Paper_HAT_(B)_Manual
+pub static EPD_2IN13B_V4: Panel = Panel {
+    name: "2.13\" (B) V4",
+    width: 122,
+    height: 250,
+    controller: Controller::Ssd16xx,
+    modes: &[DisplayMode::Full],
+    accent: true,
+    lut_grey: None,
+};
+
+/// Waveshare 4.2 inch e-Paper (B) V2
+///
+/// Documentation: https://www.waveshare.com/wiki/4.2inch_e-Paper_Module_(B)_Manual
+pub static EPD_4IN2B_V2: Panel = Panel {
+    name: "4.2\" (B) V2",
+    width: 400,
+    height: 300,
+    controller: Controller::Ssd16xx,
+    modes: &[DisplayMode::Full],
+    accent: true,
+    lut_grey: None,
+};
+
+/// Waveshare 7.5 inch e-Paper (B) V2
+///
+/// Documentation: https://www.waveshare.com/wiki/7.5inch_e-Paper_HAT_(B)_Manual
+pub static EPD_7IN5B_V2: Panel = Panel {
+    name: "7.5\" (B) V2",
+    width: 800,
+    height: 480,
+    controller: Controller::Uc8179,
+    modes: &[DisplayMode::Full],
+    accent: true,
+    lut_grey: None,
+};
+
+
+impl PanelKind {
+    pub fn panel(self) -> &'static Panel {
+        match self {
+            Self::Epd2in13V4 => &EPD_2IN13_V4,
+            Self::Epd2in13bV4 => &EPD_2IN13B_V4,
+            Self::Epd2in7V2 => &EPD_2IN7_V2,
+            Self::Epd2in9V2 => &EPD_2IN9_V2,
+            Self::Epd4in2V2 => &EPD_4IN2_V2,
+            Self::Epd4in2bV2 => &EPD_4IN2B_V2,
+            Self::Epd7in5V2 => &EPD_7IN5_V2,
+            Self::Epd7in5bV2 => &EPD_7IN5B_V2,
+        }
+    }
+}
+
+
+impl Panel {
+    pub fn supports(&self, mode: DisplayMode) -> bool {
+        self.modes.contains(&mode)
+    }
+
+    /// Fail for a mode the panel can't be driven in
+    pub fn check_mode(&self, mode: DisplayMode) -> Result<(), DriverError> {
+        if !self.supports(mode) {
+            return Err(DriverError::WrongInput(format!(
+                "The {} panel does not support the {:?} mode",
+                self.name, mode,
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Whether a part of the panel can be updated without flashing the rest
+    pub fn supports_partial(&self) -> bool {
+        self.controller == Controller::Ssd16xx && !self.accent
+    }
+
+    /// Get vertical image
+    pub fn image_white_v(&self) -> DisplayImage {
+        DisplayImage::from_pixel(self.width, self.height, WHITE)
+    }
+
+    /// Get horizontal image
+    pub fn image_white_h(&self) -> DisplayImage {
+        DisplayImage::from_pixel(self.height, self.width, WHITE)
+    }
+
+    /// Get an image in whichever orientation is wider
+    pub fn image_white_landscape(&self) -> DisplayImage {
+        if self.width > self.height {
+            self.image_white_v()
+        } else {
+            self.image_white_h()
+        }
+    }
+
+    /// Size of a single RAM plane in bytes as (bytes per row, rows)
+    pub fn size_bytes(&self) -> (usize, usize) {
+        // Pad width so that each row takes a whole number of bytes
+        let width = self.width.div_ceil(8) as usize;
+
+        // Height stays unchanged
+        let height = self.height as usize;
+
+        (width, height)
+    }
+
+    /// Whether the image has to be rotated to fit the panel, or `None` if it doesn't fit at all
+    pub fn is_horizontal(&self, img: &DisplayImage) -> Option<bool> {
+        match (img.width(), img.height()) {
+            (w, h) if (w, h) == (self.width, self.height) => Some(false),
+            (w, h) if (w, h) == (self.height, self.width) => Some(true),
+            _ => None,
+        }
+    }
+}
+
+
+const LUT_2IN7_V2_GREY: &[u8] = &[
+    0x40, 0x48, 0x80, 0x0,  0x0,  0x0,  0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x8,  0x48, 0x10, 0x0,  0x0,  0x0,  0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x2,  0x48, 0x4,  0x0,  0x0,  0x0,  0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x20, 0x48, 0x1,  0x0,  0x0,  0x0,  0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x0,  0x0,  0x0,  0x0,  0x0,  0x0,  0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0xA,  0x19, 0x0,  0x3,  0x8,  0x0,  0x0,
+    0x14, 0x1,  0x0,  0x14, 0x1,  0x0,  0x3,
+    0xA,  0x3,  0x0,  0x8,  0x19, 0x0,  0x0,
+    0x1,  0x0,  0x0,  0x0,  0x0,  0x0,  0x1,
+    0x0,  0x0,  0x0,  0x0,  0x0,  0x0,  0x0,
+    0x0,  0x0,  0x0,  0x0,  0x0,  0x0,  0x0,
+    0x0,  0x0,  0x0,  0x0,  0x0,  0x0,  0x0,
+    0x0,  0x0,  0x0,  0x0,  0x0,  0x0,  0x0,
+    0x0,  0x0,  0x0,  0x0,  0x0,  0x0,  0x0,
+    0x0,  0x0,  0x0,  0x0,  0x0,  0x0,  0x0,
+    0x0,  0x0,  0x0,  0x0,  0x0,  0x0,  0x0,
+    0x0,  0x0,  0x0,  0x0,  0x0,  0x0,  0x0,
+    0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x0, 0x0, 0x0,
+    0x22, 0x17, 0x41, 0x0,  0x32, 0x1C,
+];
@@ -0,0 +1,782 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use log::{debug, warn};
+
+use crate::app_error::AppError;
+
+
+/// Recurrences are never followed further than this many periods from the start
+const MAX_PERIODS: u32 = 100_000;
+
+
+/// One occurrence of an event, in local time
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub summary: String,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub all_day: bool,
+}
+
+
+/// Events from iCalendar files
+#[derive(Debug, Default)]
+pub struct Calendar {
+    events: Vec<VEvent>,
+}
+
+impl Calendar {
+    /// Read `.ics` files, and the ones in directories as vdirsyncer leaves them
+    pub fn load(paths: &[PathBuf]) -> Result<Self, AppError> {
+        let mut calendar = Self::default();
+
+        for path in paths {
+            if path.is_dir() {
+                let mut entries: Vec<_> = std::fs::read_dir(path)?
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<Result<_, _>>()?;
+                entries.retain(|entry| entry.is_dir() || entry.extension().is_some_and(|e| e.eq_ignore_ascii_case("ics")));
+                entries.sort();
+
+                calendar.events.extend(Self::load(&entries)?.events);
+            } else {
+                // One calendar that can't be read doesn't hide the others
+                match Self::read(path) {
+                    Ok(read) => calendar.events.extend(read.events),
+                    Err(e) => warn!("Skipping {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        Ok(calendar)
+    }
+
+    fn read(path: &Path) -> Result<Self, AppError> {
+        debug!("Reading {}", path.display());
+
+        let s = std::fs::read_to_string(path)?;
+        Self::parse(&s).map_err(|e| AppError::Data(format!("{} in {}", e, path.display())))
+    }
+
+    /// Events of the VCALENDAR, anything but VEVENT is skipped
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut events = Vec::new();
+        let mut event: Option<Vec<Property>> = None;
+        // Components inside the event, like alarms, are skipped
+        let mut nested = 0;
+
+        for line in unfold(s) {
+            let property = Property::parse(&line)?;
+
+            match (property.name.as_str(), property.value.as_str()) {
+                ("BEGIN", "VEVENT") if event.is_none() => event = Some(Vec::new()),
+                ("END", "VEVENT") if nested == 0 => {
+                    let properties = event.take().ok_or("END:VEVENT without BEGIN:VEVENT")?;
+                    events.push(VEvent::new(&properties)?);
+                },
+                ("BEGIN", _) if event.is_some() => nested += 1,
+                ("END", _) if event.is_some() => nested -= 1,
+                _ => if let (Some(properties), 0) = (&mut event, nested) {
+                    properties.push(property);
+                },
+            }
+        }
+
+        Ok(Self { events })
+    }
+
+    /// Events happening at any time of the day, sorted by start
+    pub fn events_on(&self, date: NaiveDate) -> Vec<Event> {
+        let from = date.and_hms_opt(0, 0, 0).unwrap();
+        let to = from + Duration::days(1);
+
+        // Moved or cancelled occurrences by the event they belong to
+        let mut overrides: HashMap<&str, Vec<NaiveDateTime>> = HashMap::new();
+        for event in &self.events {
+            if let (Some(uid), Some(id)) = (&event.uid, &event.recurrence_id) {
+                overrides.entry(uid).or_default().push(id.to_local());
+            }
+        }
+
+        let mut events = Vec::new();
+
+        for event in self.events.iter().filter(|event| !event.cancelled) {
+            let overridden = match (&event.uid, &event.recurrence_id) {
+                (Some(uid), None) => overrides.get(uid.as_str()).map(Vec::as_slice).unwrap_or_default(),
+                _ => &[],
+            };
+
+            for start in event.starts(from, to) {
+                if event.exdates.iter().any(|exdate| exdate.to_local() == start) || overridden.contains(&start) {
+                    continue;
+                }
+
+                events.push(Event {
+                    summary: event.summary.clone(),
+                    start,
+                    end: event.end(start),
+                    all_day: event.start.is_date(),
+                });
+            }
+        }
+
+        events.sort_by_key(|event| (!event.all_day, event.start));
+        events
+    }
+}
+
+
+/// Date or time as written in the file
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum When {
+    Date(NaiveDate),
+    Utc(NaiveDateTime),
+    Zoned(NaiveDateTime, Tz),
+    /// Floating time, the same wherever the calendar is looked at
+    Floating(NaiveDateTime),
+}
+
+impl When {
+    fn parse(value: &str, params: &HashMap<String, String>) -> Result<Self, String> {
+        let wrong = |e| format!("Wrong date '{}': {}", value, e);
+
+        if params.get("VALUE").is_some_and(|v| v == "DATE") || value.len() == 8 {
+            return NaiveDate::parse_from_str(value, "%Y%m%d").map(Self::Date).map_err(wrong);
+        }
+
+        if let Some(value) = value.strip_suffix('Z') {
+            return NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map(Self::Utc).map_err(wrong);
+        }
+
+        let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(wrong)?;
+
+        match params.get("TZID") {
+            None => Ok(Self::Floating(time)),
+            Some(tzid) => match tzid.trim_start_matches('/').parse::<Tz>() {
+                Ok(tz) => Ok(Self::Zoned(time, tz)),
+                Err(_) => {
+                    warn!("Unknown time zone '{}', taking {} as local time", tzid, value);
+                    Ok(Self::Floating(time))
+                },
+            },
+        }
+    }
+
+    fn is_date(&self) -> bool {
+        matches!(self, Self::Date(_))
+    }
+
+    /// Wall time in its own zone, midnight for dates
+    fn naive(&self) -> NaiveDateTime {
+        match *self {
+            Self::Date(date) => date.and_hms_opt(0, 0, 0).unwrap(),
+            Self::Utc(time) | Self::Zoned(time, _) | Self::Floating(time) => time,
+        }
+    }
+
+    /// The same zone at another wall time
+    fn with_naive(&self, time: NaiveDateTime) -> Self {
+        match *self {
+            Self::Date(_) => Self::Date(time.date()),
+            Self::Utc(_) => Self::Utc(time),
+            Self::Zoned(_, tz) => Self::Zoned(time, tz),
+            Self::Floating(_) => Self::Floating(time),
+        }
+    }
+
+    fn to_local(self) -> NaiveDateTime {
+        match self {
+            Self::Date(_) | Self::Floating(_) => self.naive(),
+            Self::Utc(time) => DateTime::<Local>::from(Utc.from_utc_datetime(&time)).naive_local(),
+            Self::Zoned(time, tz) => {
+                // Times skipped by a DST change are taken as an hour later
+                let zoned = tz.from_local_datetime(&time).earliest()
+                    .or_else(|| tz.from_local_datetime(&(time + Duration::hours(1))).earliest());
+
+                match zoned {
+                    Some(zoned) => zoned.with_timezone(&Local).naive_local(),
+                    None => time,
+                }
+            },
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+
+/// RRULE, without the rarely used parts like BYSETPOS or hourly recurrences
+#[derive(Clone, Debug)]
+struct Rule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<When>,
+    /// Weekdays, with the number in the month for monthly and yearly rules: `2TU`, `-1FR`
+    by_day: Vec<(Option<i32>, Weekday)>,
+    by_month_day: Vec<i32>,
+    by_month: Vec<u32>,
+}
+
+impl Rule {
+    fn parse(value: &str) -> Result<Self, String> {
+        let mut rule = Self {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+        };
+        let mut frequency = None;
+
+        let wrong = |part: &str| format!("Wrong RRULE part '{}'", part);
+        let numbers = |v: &str, part: &str| v.split(',').map(|n| n.parse().map_err(|_| wrong(part))).collect::<Result<Vec<_>, _>>();
+
+        for part in value.split(';').filter(|part| !part.is_empty()) {
+            let (key, v) = part.split_once('=').ok_or_else(|| wrong(part))?;
+
+            match key {
+                "FREQ" => frequency = Some(match v {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    _ => return Err(format!("Unsupported frequency '{}'", v)),
+                }),
+                "INTERVAL" => rule.interval = v.parse::<u32>().map_err(|_| wrong(part))?.max(1),
+                "COUNT" => rule.count = Some(v.parse().map_err(|_| wrong(part))?),
+                "UNTIL" => rule.until = Some(When::parse(v, &HashMap::new())?),
+                "BYDAY" => rule.by_day = v.split(',').map(|day| parse_day(day).ok_or_else(|| wrong(part))).collect::<Result<_, _>>()?,
+                "BYMONTHDAY" => rule.by_month_day = numbers(v, part)?,
+                "BYMONTH" => rule.by_month = numbers(v, part)?.into_iter().map(|m: i32| m as u32).collect(),
+                _ => debug!("Ignoring RRULE part '{}'", part),
+            }
+        }
+
+        rule.frequency = frequency.ok_or_else(|| format!("No FREQ in RRULE '{}'", value))?;
+        Ok(rule)
+    }
+
+    /// Dates the rule gives in the `k`th period from `first`, sorted
+    fn dates(&self, first: NaiveDate, k: u32) -> Option<Vec<NaiveDate>> {
+        let step = k.checked_mul(self.interval)?;
+
+        let mut dates = match self.frequency {
+            Frequency::Daily => {
+                let date = first.checked_add_signed(Duration::days(step as i64))?;
+                let on_day = self.by_day.is_empty() || self.by_day.iter().any(|(_, day)| *day == date.weekday());
+                let on_month_day = self.by_month_day.is_empty() || self.by_month_day.iter().any(|d| month_day(date, *d) == Some(date));
+                if on_day && on_month_day { vec![date] } else { vec![] }
+            },
+            Frequency::Weekly => {
+                let monday = first.checked_add_signed(Duration::days(7 * step as i64 - first.weekday().num_days_from_monday() as i64))?;
+                let days: Vec<_> = match self.by_day.is_empty() {
+                    true => vec![first.weekday()],
+                    false => self.by_day.iter().map(|(_, day)| *day).collect(),
+                };
+                days.into_iter().map(|day| monday + Duration::days(day.num_days_from_monday() as i64)).collect()
+            },
+            Frequency::Monthly => {
+                let month = first.with_day(1)?.checked_add_months(Months::new(step))?;
+                self.month_dates(month, first.day())
+            },
+            Frequency::Yearly => {
+                let year = first.year().checked_add(step as i32)?;
+                let months = match self.by_month.is_empty() {
+                    true => vec![first.month()],
+                    false => self.by_month.clone(),
+                };
+                months.into_iter()
+                    .filter_map(|month| NaiveDate::from_ymd_opt(year, month, 1))
+                    .flat_map(|month| self.month_dates(month, first.day()))
+                    .collect()
+            },
+        };
+
+        if !self.by_month.is_empty() {
+            dates.retain(|date| self.by_month.contains(&date.month()));
+        }
+
+        dates.sort();
+        dates.dedup();
+        Some(dates)
+    }
+
+    /// Dates of the month starting on `month`, the same day as the first occurrence without BYDAY or BYMONTHDAY
+    fn month_dates(&self, month: NaiveDate, day: u32) -> Vec<NaiveDate> {
+        let by_month_day = |date: &NaiveDate| self.by_month_day.iter().any(|d| month_day(*date, *d) == Some(*date));
+
+        if !self.by_day.is_empty() {
+            let days: Vec<_> = month.iter_days().take_while(|date| date.month() == month.month()).collect();
+
+            return self.by_day.iter()
+                .flat_map(|(n, weekday)| {
+                    let matching: Vec<_> = days.iter().copied().filter(|date| date.weekday() == *weekday).collect();
+                    match n {
+                        None => matching,
+                        Some(n) if *n > 0 => matching.get(*n as usize - 1).copied().into_iter().collect(),
+                        Some(n) => matching.len().checked_sub(n.unsigned_abs() as usize).and_then(|i| matching.get(i)).copied().into_iter().collect(),
+                    }
+                })
+                .filter(|date| self.by_month_day.is_empty() || by_month_day(date))
+                .collect();
+        }
+
+        if !self.by_month_day.is_empty() {
+            return self.by_month_day.iter().filter_map(|d| month_day(month, *d)).collect();
+        }
+
+        // Months without the day are skipped
+        month.with_day(day).into_iter().collect()
+    }
+}
+
+
+/// The day of the month of `date`, counting from the end when negative
+fn month_day(date: NaiveDate, day: i32) -> Option<NaiveDate> {
+    if day > 0 {
+        return date.with_day(day as u32);
+    }
+
+    let next = date.with_day(1)?.checked_add_months(Months::new(1))?;
+    next.checked_sub_signed(Duration::days(day.unsigned_abs() as i64))
+        .filter(|d| d.month() == date.month())
+}
+
+
+/// `MO`, `2TU`, `-1FR`
+fn parse_day(s: &str) -> Option<(Option<i32>, Weekday)> {
+    let split = s.len().checked_sub(2)?;
+    let (n, day) = s.split_at(split);
+
+    let day = match day {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+
+    match n.trim_start_matches('+') {
+        "" => Some((None, day)),
+        n => n.parse().ok().map(|n| (Some(n), day)),
+    }
+}
+
+
+/// `PT1H30M`, `P1D`, `P2W`
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let wrong = || format!("Wrong duration '{}'", s);
+
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s.trim_start_matches('+')),
+    };
+    let s = s.strip_prefix('P').ok_or_else(wrong)?;
+
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+
+    for c in s.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {},
+            'W' | 'D' | 'H' | 'M' | 'S' => {
+                let n: i64 = number.parse().map_err(|_| wrong())?;
+                number.clear();
+                let part = match c {
+                    'W' => Duration::try_weeks(n),
+                    'D' => Duration::try_days(n),
+                    'H' => Duration::try_hours(n),
+                    'M' => Duration::try_minutes(n),
+                    _ => Duration::try_seconds(n),
+                };
+                duration = part.and_then(|part| duration.checked_add(&part)).ok_or_else(wrong)?;
+            },
+            _ => return Err(wrong()),
+        }
+    }
+
+    if !number.is_empty() {
+        return Err(wrong());
+    }
+
+    Ok(if negative { -duration } else { duration })
+}
+
+
+/// Long lines are folded by starting the continuation with a space
+fn unfold(s: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in s.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.trim().is_empty() => {},
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+
+/// `NAME;PARAM=VALUE:VALUE`
+#[derive(Debug)]
+struct Property {
+    name: String,
+    params: HashMap<String, String>,
+    value: String,
+}
+
+impl Property {
+    fn parse(line: &str) -> Result<Self, String> {
+        // The value starts at the first colon outside of quotes
+        let mut quoted = false;
+        let colon = line.char_indices()
+            .find(|(_, c)| {
+                if *c == '"' {
+                    quoted = !quoted;
+                }
+                *c == ':' && !quoted
+            })
+            .map(|(i, _)| i)
+            .ok_or_else(|| format!("No value in '{}'", line))?;
+
+        let (head, value) = (&line[..colon], &line[colon + 1..]);
+        let mut parts = head.split(';');
+        let name = parts.next().unwrap_or_default().to_uppercase();
+
+        let params = parts
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"').to_string()))
+            .collect();
+
+        Ok(Self { name, params, value: value.to_string() })
+    }
+}
+
+
+/// VEVENT as written in the file
+#[derive(Clone, Debug)]
+struct VEvent {
+    uid: Option<String>,
+    summary: String,
+    start: When,
+    /// The same for every occurrence
+    duration: Duration,
+    rule: Option<Rule>,
+    exdates: Vec<When>,
+    /// Which occurrence of the event with the same UID this one replaces
+    recurrence_id: Option<When>,
+    cancelled: bool,
+}
+
+impl VEvent {
+    fn new(properties: &[Property]) -> Result<Self, String> {
+        let get = |name: &str| properties.iter().find(|p| p.name == name);
+
+        let start = get("DTSTART").ok_or("VEVENT without DTSTART")?;
+        let start = When::parse(&start.value, &start.params)?;
+
+        let duration = match (get("DTEND"), get("DURATION")) {
+            (Some(end), _) => When::parse(&end.value, &end.params)?.to_local() - start.to_local(),
+            (None, Some(duration)) => parse_duration(&duration.value)?,
+            // All-day events take the day, others take no time
+            (None, None) if start.is_date() => Duration::days(1),
+            (None, None) => Duration::zero(),
+        };
+
+        let mut exdates = Vec::new();
+        for exdate in properties.iter().filter(|p| p.name == "EXDATE") {
+            for value in exdate.value.split(',') {
+                exdates.push(When::parse(value, &exdate.params)?);
+            }
+        }
+
+        Ok(Self {
+            uid: get("UID").map(|p| p.value.clone()),
+            summary: get("SUMMARY").map(|p| unescape(&p.value)).unwrap_or_default(),
+            start,
+            duration: duration.max(Duration::zero()),
+            rule: get("RRULE").map(|p| Rule::parse(&p.value)).transpose()?,
+            exdates,
+            recurrence_id: get("RECURRENCE-ID").map(|p| When::parse(&p.value, &p.params)).transpose()?,
+            cancelled: get("STATUS").is_some_and(|p| p.value == "CANCELLED"),
+        })
+    }
+
+    /// End of the occurrence starting at `start`, or the end of time for events lasting past it
+    fn end(&self, start: NaiveDateTime) -> NaiveDateTime {
+        start.checked_add_signed(self.duration).unwrap_or(NaiveDateTime::MAX)
+    }
+
+    /// Local starts of the occurrences overlapping `from..to`
+    fn starts(&self, from: NaiveDateTime, to: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let overlaps = |start: NaiveDateTime| start < to && (self.end(start) > from || start >= from);
+
+        let Some(rule) = &self.rule else {
+            let start = self.start.to_local();
+            return if overlaps(start) { vec![start] } else { vec![] };
+        };
+
+        // Dates end the day they name, times are inclusive
+        let until = rule.until.map(|until| match until {
+            When::Date(date) => date.and_hms_opt(0, 0, 0).unwrap() + Duration::days(1),
+            until => until.to_local() + Duration::seconds(1),
+        });
+
+        let first = self.start.naive();
+        let mut starts = Vec::new();
+        let mut count = 0;
+
+        for k in 0..MAX_PERIODS {
+            let Some(dates) = rule.dates(first.date(), k) else {
+                break;
+            };
+
+            for date in dates {
+                let time = date.and_time(first.time());
+                if time < first {
+                    continue;
+                }
+
+                let start = self.start.with_naive(time).to_local();
+
+                if until.is_some_and(|until| start >= until) || rule.count.is_some_and(|max| count >= max) || start >= to {
+                    return starts;
+                }
+
+                count += 1;
+                if overlaps(start) {
+                    starts.push(start);
+                }
+            }
+        }
+
+        starts
+    }
+}
+
+
+/// `\,` `\;` `\n` and `\\` in text values
+fn unescape(s: &str) -> String {
+    let mut text = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('n') | Some('N') => text.push('\n'),
+                Some(c) => text.push(c),
+                None => {},
+            },
+            (c, false) => text.push(c),
+        }
+    }
+
+    text
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        date(y, m, d).and_hms_opt(h, min, 0).unwrap()
+    }
+
+    fn calendar(events: &str) -> Calendar {
+        Calendar::parse(&format!("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{}END:VCALENDAR\r\n", events)).unwrap()
+    }
+
+    fn event(lines: &str) -> Calendar {
+        calendar(&format!("BEGIN:VEVENT\n{}END:VEVENT\n", lines))
+    }
+
+    /// Days of October 2026 with an occurrence
+    fn days(calendar: &Calendar) -> Vec<u32> {
+        (1..=31).filter(|d| !calendar.events_on(date(2026, 10, *d)).is_empty()).collect()
+    }
+
+    #[test]
+    fn single_events() {
+        let calendar = calendar("\
+BEGIN:VEVENT
+UID:1
+SUMMARY:Standup\\, short
+DTSTART:20261018T093000
+DTEND:20261018T094500
+BEGIN:VALARM
+SUMMARY:Not an event
+TRIGGER:-PT5M
+END:VALARM
+END:VEVENT
+BEGIN:VEVENT
+SUMMARY:Long title that is
+  folded
+DTSTART:20261018T140000
+DURATION:PT1H30M
+END:VEVENT
+BEGIN:VTODO
+SUMMARY:Not an event either
+END:VTODO
+");
+
+        let events = calendar.events_on(date(2026, 10, 18));
+
+        assert_eq!(events, [
+            Event { summary: "Standup, short".to_string(), start: at(2026, 10, 18, 9, 30), end: at(2026, 10, 18, 9, 45), all_day: false },
+            Event { summary: "Long title that is folded".to_string(), start: at(2026, 10, 18, 14, 0), end: at(2026, 10, 18, 15, 30), all_day: false },
+        ]);
+        assert!(calendar.events_on(date(2026, 10, 19)).is_empty());
+    }
+
+    #[test]
+    fn all_day() {
+        let calendar = event("SUMMARY:Holiday\nDTSTART;VALUE=DATE:20261016\nDTEND;VALUE=DATE:20261019\n");
+
+        assert_eq!(days(&calendar), [16, 17, 18]);
+        assert!(calendar.events_on(date(2026, 10, 17))[0].all_day);
+
+        let calendar = event("SUMMARY:Birthday\nDTSTART;VALUE=DATE:20001018\nRRULE:FREQ=YEARLY\n");
+        assert_eq!(days(&calendar), [18]);
+    }
+
+    #[test]
+    fn recurrences() {
+        let weekly = event("DTSTART:20261005T100000\nRRULE:FREQ=WEEKLY;BYDAY=MO,TH\n");
+        assert_eq!(days(&weekly), [5, 8, 12, 15, 19, 22, 26, 29]);
+
+        let counted = event("DTSTART:20261005T100000\nRRULE:FREQ=DAILY;INTERVAL=3;COUNT=4\n");
+        assert_eq!(days(&counted), [5, 8, 11, 14]);
+
+        let until = event("DTSTART:20261020T100000\nRRULE:FREQ=DAILY;UNTIL=20261023\n");
+        assert_eq!(days(&until), [20, 21, 22, 23]);
+
+        let nth = event("DTSTART:20260101T100000\nRRULE:FREQ=MONTHLY;BYDAY=2TU,-1FR\n");
+        assert_eq!(days(&nth), [13, 30]);
+
+        let month_day = event("DTSTART:20260131T100000\nRRULE:FREQ=MONTHLY;BYMONTHDAY=-1\n");
+        assert_eq!(days(&month_day), [31]);
+
+        // Months without the 31st are skipped, October is the 6th
+        let skipping = event("DTSTART:20260131T100000\nRRULE:FREQ=MONTHLY;COUNT=6\n");
+        assert_eq!(days(&skipping), [31]);
+        let skipping = event("DTSTART:20260131T100000\nRRULE:FREQ=MONTHLY;COUNT=5\n");
+        assert!(days(&skipping).is_empty());
+    }
+
+    #[test]
+    fn exceptions() {
+        let calendar = calendar("\
+BEGIN:VEVENT
+UID:standup
+SUMMARY:Standup
+DTSTART:20261012T093000
+DURATION:PT15M
+RRULE:FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR
+EXDATE:20261013T093000,20261014T093000
+EXDATE:20261015T093000
+END:VEVENT
+BEGIN:VEVENT
+UID:standup
+RECURRENCE-ID:20261016T093000
+SUMMARY:Standup, later
+DTSTART:20261016T110000
+DURATION:PT15M
+END:VEVENT
+BEGIN:VEVENT
+UID:standup
+RECURRENCE-ID:20261019T093000
+STATUS:CANCELLED
+DTSTART:20261019T093000
+END:VEVENT
+");
+
+        assert_eq!(days(&calendar), [12, 16, 20, 21, 22, 23, 26, 27, 28, 29, 30]);
+
+        let moved = calendar.events_on(date(2026, 10, 16));
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].summary, "Standup, later");
+        assert_eq!(moved[0].start, at(2026, 10, 16, 11, 0));
+    }
+
+    #[test]
+    fn time_zones() {
+        let berlin = event("DTSTART;TZID=Europe/Berlin:20261018T100000\nDTEND;TZID=Europe/Berlin:20261018T110000\n");
+        let utc = event("DTSTART:20261018T080000Z\nDURATION:PT1H\n");
+
+        // Both are 08:00 UTC, wherever the test runs
+        let expected = DateTime::<Local>::from(Utc.with_ymd_and_hms(2026, 10, 18, 8, 0, 0).unwrap()).naive_local();
+        let start = |calendar: &Calendar| calendar.events_on(expected.date())[0].start;
+
+        assert_eq!(start(&berlin), expected);
+        assert_eq!(start(&utc), expected);
+
+        // Still 10:00 in Berlin after the clocks go back on the 25th
+        let weekly = event("DTSTART;TZID=Europe/Berlin:20261018T100000\nRRULE:FREQ=WEEKLY\n");
+        let expected = DateTime::<Local>::from(Utc.with_ymd_and_hms(2026, 10, 25, 9, 0, 0).unwrap()).naive_local();
+        assert_eq!(weekly.events_on(expected.date())[0].start, expected);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("PT1H30M").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("P1DT2H").unwrap(), Duration::hours(26));
+        assert_eq!(parse_duration("P2W").unwrap(), Duration::days(14));
+        assert_eq!(parse_duration("-PT15M").unwrap(), Duration::minutes(-15));
+        assert!(parse_duration("1H").is_err());
+        assert!(parse_duration("PT1").is_err());
+
+        // Too long for a duration, or for an event
+        assert!(parse_duration("P9999999999999999W").is_err());
+        assert!(parse_duration("P100000000000000DT100000000000000H").is_err());
+        let events = calendar("BEGIN:VEVENT\r\nDTSTART:20261018T090000\r\nDURATION:P100000000W\r\nEND:VEVENT\r\n")
+            .events_on(date(2026, 10, 18));
+        assert_eq!(events[0].end, NaiveDateTime::MAX);
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(Calendar::parse("BEGIN:VEVENT\nSUMMARY:No start\nEND:VEVENT\n").is_err());
+        assert!(Calendar::parse("BEGIN:VEVENT\nDTSTART:yesterday\nEND:VEVENT\n").is_err());
+        assert!(Calendar::parse("BEGIN:VEVENT\nDTSTART:20261018\nRRULE:FREQ=SECONDLY\nEND:VEVENT\n").is_err());
+        assert!(Calendar::parse("no colon\n").is_err());
+        assert!(Calendar::parse("").unwrap().events.is_empty());
+    }
+
+    #[test]
+    fn skip_unreadable() {
+        let dir = std::env::temp_dir().join(format!("pinterface-ics-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("broken.ics"), "BEGIN:VEVENT\nSUMMARY:No start\nEND:VEVENT\n").unwrap();
+        std::fs::write(dir.join("lunch.ics"), "BEGIN:VEVENT\nSUMMARY:Lunch\nDTSTART:20261018T120000\nEND:VEVENT\n").unwrap();
+
+        let calendar = Calendar::load(&[dir.clone(), dir.join("missing.ics")]).unwrap();
+        let events = calendar.events_on(date(2026, 10, 18));
+        assert_eq!(events.iter().map(|event| event.summary.as_str()).collect::<Vec<_>>(), ["Lunch"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,20 +1,42 @@
 use clap::Parser;
 
+mod api;
 mod app_error;
 mod args;
 mod command;
+mod data;
 mod driver;
+mod ics;
+mod preview;
 mod render;
 mod vault;
 mod util;
 
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use api::{Action, Status};
+use app_error::AppError;
 use args::{Command, DisplayMode};
-use driver::Display as Dev;
-use log::{error, info};
+use chrono::Timelike;
+use command::{focus::Focus, Context};
+use driver::{buttons::{Button, Buttons}, panel::Panel, Display as Dev, DisplayImage, WHITE};
+use driver::lut::Lut;
+use image::DynamicImage;
+use lazy_static::lazy_static;
+use imageproc::rect::Rect;
+use log::{error, info, warn};
 use util::*;
 
 
-const IFNAME: &'static str = "wlan0";
+const IFNAME: &str = "wlan0";
+
+lazy_static! {
+    /// The content drawn last without errors and its frame
+    static ref LAST_GOOD: Mutex<Option<(String, DisplayImage)>> = Mutex::new(None);
+}
 
 // With JetBrains Mono Regular:
 //
@@ -24,44 +46,343 @@ const IFNAME: &'static str = "wlan0";
 // | 16.0       | 36            |
 
 
-fn try_main(args: &args::Args) -> Result<(), Box<dyn std::error::Error>> {
+/// What the panel shows
+enum Content {
+    View(Command),
+    Text(String),
+    Picture(DynamicImage),
+    Focus(Focus),
+}
 
-    let mut dev = Dev::new()?;
+impl std::fmt::Display for Content {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::View(command) => write!(f, "{:?}", command),
+            Self::Text(_) => write!(f, "Text"),
+            Self::Picture(_) => write!(f, "Picture"),
+            Self::Focus(_) => write!(f, "Focus"),
+        }
+    }
+}
 
-    loop {
-        let mut img = Dev::image_white_h();
 
-        match args.command {
+/// Draw the content, leaving room for the status bar if there is one, with a warning when the sync looks stalled
+fn draw(args: &args::Args, ctx: &Context, content: &Content, img: &mut DisplayImage) -> Result<(), Box<dyn std::error::Error>> {
+    // One walk over the vault serves both the sync check and the status bar
+    let changes = if args.sync_check || args.status_bar {
+        ctx.vault.changes().map_err(|e| warn!("Could not look through the vault: {}", e)).ok()
+    } else {
+        None
+    };
+
+    let problems = if args.sync_check {
+        command::sync::check(ctx, &args.sync_unit, chrono::Duration::hours(args.sync_max_age.into()), changes.as_ref())
+    } else {
+        Vec::new()
+    };
+    for problem in &problems {
+        warn!("Sync: {}", problem);
+    }
+
+    if !args.status_bar {
+        draw_view(args, ctx, content, img)?;
+        if !problems.is_empty() {
+            command::sync::banner(img, &problems)?;
+        }
+        return Ok(());
+    }
+
+    let area = view_area(img, args);
+    let mut view = DisplayImage::from_pixel(area.width(), area.height(), WHITE);
+    draw_view(args, ctx, content, &mut view)?;
+    image::imageops::replace(img, &view, area.left().into(), area.top().into());
+
+    command::status::status_bar(img, ctx, args.mode, &problems, changes.as_ref())
+}
+
+
+/// Part of the panel the content is drawn in
+fn view_area(img: &DisplayImage, args: &args::Args) -> Rect {
+    if args.status_bar {
+        command::status::view_rect(img)
+    } else {
+        Rect::at(0, 0).of_size(img.width(), img.height())
+    }
+}
+
+
+fn draw_view(args: &args::Args, ctx: &Context, content: &Content, img: &mut DisplayImage) -> Result<(), Box<dyn std::error::Error>> {
+    match content {
+        Content::View(command) => match command {
             Command::Clear => {},
             Command::Debug => {
-                command::debug::debug(&mut img)?;
+                command::debug::debug(img, args.pattern)?;
             },
             Command::Tasks => {
-                command::tasks::tasks(&mut img)?;
+                let statuses: Vec<_> = args.task_statuses.iter().map(|status| status.status()).collect();
+                let options = command::tasks::Options {
+                    properties: &args.properties,
+                    headings: &args.progress_headings,
+                    sections: &args.task_sections,
+                    statuses: &statuses,
+                    by_status: args.sort_by_status,
+                };
+                command::tasks::tasks(img, ctx, &options)?;
+            },
+            Command::Calendar => {
+                command::calendar::calendar(img, ctx, &args.ics)?;
+            },
+            Command::Month => {
+                command::month::month(img, ctx)?;
+            },
+            Command::Agenda => {
+                command::agenda::agenda(img, ctx, args.agenda_days, args.overdue_days)?;
+            },
+            Command::Image => {
+                let path = required(&args.image, "image")?;
+                command::image::image(img, path, &picture_options(args))?;
             },
-            Command::Calendar => {},
             Command::Network => {
-                command::network::network(&mut img, IFNAME)?;
+                command::network::network(img, ctx)?;
             },
-        };
+            Command::Clock => {
+                command::clock::clock(img, ctx)?;
+            },
+            Command::Habits => {
+                command::habits::habits(img, ctx, &args.habits, args.habit_days)?;
+            },
+            Command::Widgets => {
+                let path = required(&args.widgets, "widgets")?;
+                command::widgets::widgets(img, ctx, path)?;
+            },
+            Command::Screen => {
+                let path = required(&args.screen, "screen")?;
+                command::screen::screen(img, ctx, path, args.mode.levels())?;
+            },
+            Command::Section => {
+                let path = required(&args.section, "section")?;
+                command::section::section(img, ctx, path)?;
+            },
+            Command::Script => {
+                let script = required(&args.script, "script")?;
+                command::script::script(img, script, args.script_timeout, args.text_style, &picture_options(args))?;
+            },
+            Command::Focus => {
+                let state = Focus::new(command::focus::open_tasks(ctx)?, focus_length(args.focus_minutes), focus_length(args.break_minutes));
+                command::focus::focus(img, ctx, &state)?;
+            },
+        },
+        Content::Text(text) => {
+            command::message::message(img, text)?;
+        },
+        Content::Picture(picture) => {
+            command::image::picture(img, picture.clone(), &picture_options(args))?;
+        },
+        Content::Focus(state) => {
+            command::focus::focus(img, ctx, state)?;
+        },
+    };
+
+    Ok(())
+}
 
-        // Save the image if required
 
-        if args.debug {
-            img.save("out/debug.png")?;
-        }
+/// How pictures of the image and script views and the API are drawn
+fn picture_options(args: &args::Args) -> command::image::Options {
+    command::image::Options {
+        fit: args.fit,
+        gamma: args.gamma,
+        contrast: args.contrast,
+        levels: args.mode.levels(),
+        dither: args.dither,
+    }
+}
 
-        // Initialize and clear
 
-        dev.init(DisplayMode::Fast)?;
-        dev.clear(DisplayMode::Fast)?;
+/// An argument of a view, which the API can ask for even when it was not given
+fn required<'a, T>(arg: &'a Option<T>, name: &str) -> Result<&'a T, AppError> {
+    arg.as_ref().ok_or_else(|| AppError::Data(format!("--{} is required for this view", name)))
+}
 
-        // Reinitialize and display something
 
-        dev.init(args.mode)?;
+/// Draw the content on an image for the panel, returning why it could not be drawn if it failed.
+/// A failed content keeps its last good frame with a badge, or the error is drawn instead.
+fn frame(panel: &Panel, args: &args::Args, content: &Content) -> Result<(DisplayImage, Option<String>), Box<dyn std::error::Error>> {
+    let ctx = Context::new(IFNAME);
+    let mut img = panel.image_white_landscape();
+    let name = content.to_string();
+
+    let failure = match draw(args, &ctx, content, &mut img) {
+        Ok(()) => {
+            *LAST_GOOD.lock()? = Some((name, img.clone()));
+            None
+        },
+        Err(e) => {
+            error!("Could not draw {}: {}", name, e);
+
+            img = match LAST_GOOD.lock()?.clone() {
+                Some((last, mut last_img)) if last == name => {
+                    command::message::badge(&mut last_img, ctx.now)?;
+                    last_img
+                },
+                _ => {
+                    let mut img = panel.image_white_landscape();
+                    command::message::failure(&mut img, e.as_ref(), ctx.now)?;
+                    img
+                },
+            };
+
+            Some(e.to_string())
+        },
+    };
+
+    // Save the image if required
+
+    if args.debug {
+        std::fs::create_dir_all("out")?;
+        img.save("out/debug.png")?;
+    }
+
+    Ok((img, failure))
+}
+
+
+/// Draw the content and put it on the panel, returning why the content could not be drawn if it failed
+fn refresh(dev: &mut Dev, args: &args::Args, content: &Content, mode: DisplayMode) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let (img, failure) = frame(dev.panel(), args, content)?;
+
+    update_temperature(dev, args)?;
+
+    // Initialize and clear
+
+    let quick = quick_mode(dev);
+
+    dev.init(quick)?;
+    dev.clear(quick)?;
+
+    // Reinitialize and display something
+
+    dev.init(mode)?;
+
+    dev.display(img, mode)?;
+    dev.sleep()?;
+
+    Ok(failure)
+}
+
+
+/// Refreshes depend on the temperature
+fn update_temperature(dev: &mut Dev, args: &args::Args) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(path) = &args.temperature_file {
+        dev.set_temperature(Some(read_temperature(path)?));
+    } else {
+        dev.set_temperature(args.temperature);
+    }
+
+    Ok(())
+}
+
+
+/// Move the clock to the current minute: only the digits, or everything on the hour to clear ghosting
+fn tick(dev: &mut Dev, args: &args::Args, content: &Content) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let now = chrono::Local::now();
+
+    if now.minute() == 0 {
+        return refresh(dev, args, content, args.mode);
+    }
+
+    partial(dev, args, content, command::clock::digits_rect)
+}
 
-        dev.display(img, args.mode)?;
-        dev.sleep()?;
+
+/// Redraw only the region of the content, given its size, or everything on panels without partial updates
+fn partial(dev: &mut Dev, args: &args::Args, content: &Content, region: fn(u32, u32) -> Rect) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    if !dev.panel().supports_partial() {
+        return refresh(dev, args, content, args.mode);
+    }
+
+    let (img, failure) = frame(dev.panel(), args, content)?;
+
+    // Regions are taken from the part the content is drawn in
+    let area = view_area(&img, args);
+    let region = region(area.width(), area.height());
+    let mut region = Rect::at(region.left() + area.left(), region.top() + area.top()).of_size(region.width(), region.height());
+
+    // The status bar has the time of the refresh, it is updated along with everything down to it
+    if args.status_bar {
+        region = Rect::at(0, region.top()).of_size(img.width(), img.height() - region.top() as u32);
+    }
+
+    update_temperature(dev, args)?;
+
+    dev.display_partial(img, region)?;
+    dev.sleep()?;
+
+    Ok(failure)
+}
+
+
+/// Time left until the next minute starts
+fn until_next_minute() -> Duration {
+    let now = chrono::Local::now();
+    let passed = Duration::new(now.second() as u64, now.nanosecond() % 1_000_000_000);
+
+    Duration::from_secs(60).saturating_sub(passed)
+}
+
+
+/// Fastest mode the panel supports, for clearing
+fn quick_mode(dev: &Dev) -> DisplayMode {
+    if dev.panel().supports(DisplayMode::Fast) {
+        DisplayMode::Fast
+    } else {
+        DisplayMode::Full
+    }
+}
+
+
+fn try_main(args: &args::Args) -> Result<(), Box<dyn std::error::Error>> {
+
+    if let Some(style) = args.preview {
+        // No hardware needed
+        let panel = args.panel.panel();
+        panel.check_mode(args.mode)?;
+
+        let (img, failure) = frame(panel, args, &Content::View(args.command))?;
+        print!("{}", preview::preview(&img, panel, args.mode, style));
+        return failure.map_or(Ok(()), |e| Err(e.into()));
+    }
+
+    let mut dev = Dev::new(args.panel.panel())?;
+
+    for lut in &args.lut_fast {
+        dev.add_lut(DisplayMode::Fast, lut.band.clone(), Lut::load(&lut.path)?)?;
+    }
+    for lut in &args.lut_grey {
+        dev.add_lut(DisplayMode::Grey, lut.band.clone(), Lut::load(&lut.path)?)?;
+    }
+
+    if let Some(path) = &args.socket {
+        return serve(&mut dev, args, path);
+    }
+
+    if let Command::Focus = args.command {
+        return focus(&mut dev, args);
+    }
+
+    let content = Content::View(args.command);
+
+    if let Command::Clock = args.command {
+        refresh(&mut dev, args, &content, args.mode)?;
+
+        loop {
+            std::thread::sleep(until_next_minute());
+            tick(&mut dev, args, &content)?;
+        }
+    }
+
+    loop {
+        let failure = refresh(&mut dev, args, &content, args.mode)?;
 
         // Wait
 
@@ -73,20 +394,124 @@ fn try_main(args: &args::Args) -> Result<(), Box<dyn std::error::Error>> {
         dev.clear(DisplayMode::Full)?;
         dev.sleep()?;
 
-        // Stop if not repeating
+        // Stop if not repeating, failing if the view could not be drawn
 
         if !args.repeat {
-            break;
+            return failure.map_or(Ok(()), |e| Err(e.into()));
         }
 
         // Wait
 
         sleep_ms(10_000);
     }
+}
+
+
+/// Keep the content on the panel, taking orders from the local API
+fn serve(dev: &mut Dev, args: &args::Args, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let (actions, orders) = mpsc::channel();
+    let status = Arc::new(Mutex::new(Status::default()));
+
+    api::serve(path, actions, status.clone())?;
+
+    let mut content = Content::View(args.command);
+    let mut mode = args.mode;
+    let mut ticking = false;
+
+    loop {
+        let result = if ticking {
+            tick(dev, args, &content)
+        } else {
+            refresh(dev, args, &content, mode)
+        };
+
+        {
+            let mut status = status.lock().map_err(|e| e.to_string())?;
+            status.view = content.to_string();
+            status.last_refresh = Some(chrono::Local::now().to_rfc3339());
+            status.last_error = match &result {
+                Ok(failure) => failure.clone(),
+                Err(e) => Some(e.to_string()),
+            };
+        }
+
+        if let Err(e) = result {
+            // Keep serving, the next refresh may go better
+            error!("Refresh failed: {}", e);
+        }
+
+        mode = args.mode;
+        ticking = false;
+
+        // The clock moves every minute, everything else waits for the interval
+        let timeout = match content {
+            Content::View(Command::Clock) => until_next_minute(),
+            _ => Duration::from_secs(args.interval),
+        };
+
+        match orders.recv_timeout(timeout) {
+            Ok(Action::View(command)) => content = Content::View(command),
+            Ok(Action::Text(text)) => content = Content::Text(text),
+            Ok(Action::Picture(picture)) => content = Content::Picture(picture),
+            Ok(Action::Refresh) => mode = DisplayMode::Full,
+            Ok(Action::Clear) => content = Content::View(Command::Clear),
+            Err(RecvTimeoutError::Timeout) => ticking = matches!(content, Content::View(Command::Clock)),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
 
     Ok(())
 }
 
+
+/// Run the focus timer with the keys of the HAT, logging finished work sessions into today's note
+fn focus(dev: &mut Dev, args: &args::Args) -> Result<(), Box<dyn std::error::Error>> {
+    let buttons = Buttons::new()?;
+    let ctx = Context::new(IFNAME);
+
+    let mut state = Focus::new(command::focus::open_tasks(&ctx)?, focus_length(args.focus_minutes), focus_length(args.break_minutes));
+
+    refresh(dev, args, &Content::Focus(state.clone()), args.mode)?;
+
+    loop {
+        let pressed = buttons.wait(state.until_tick(chrono::Local::now().naive_local()));
+        let now = chrono::Local::now().naive_local();
+        let phase = state.phase;
+
+        match pressed {
+            Some(Button::Key1) => state.toggle(now),
+            Some(Button::Key2) => state.stop(),
+            Some(Button::Key3) => state.next_task(),
+            Some(Button::Key4) | None => {},
+        }
+
+        if let Some(session) = state.advance(now) {
+            info!("Finished {:?}", session);
+
+            if let Err(e) = ctx.vault.append_note(session.end.date(), &session.log_line()) {
+                // Keep the timer going, the session is in the log at least
+                error!("Could not log the session: {}", e);
+            }
+        }
+
+        let content = Content::Focus(state.clone());
+
+        if state.phase != phase {
+            refresh(dev, args, &content, args.mode)?;
+        } else if pressed.is_some() {
+            partial(dev, args, &content, |width, height| Rect::at(0, 0).of_size(width, height))?;
+        } else {
+            partial(dev, args, &content, command::focus::timer_rect)?;
+        }
+    }
+}
+
+
+fn focus_length(minutes: u32) -> chrono::Duration {
+    chrono::Duration::minutes(minutes as i64)
+}
+
+
 fn main() {
     dotenv::dotenv().ok(); // Don't fail when `.env` is not present
     pretty_env_logger::init();
@@ -96,6 +521,9 @@ fn main() {
 
     match try_main(&args) {
         Ok(_) => info!("Done!"),
-        Err(e) => error!("Error: {}", e),
+        Err(e) => {
+            error!("Error: {}", e);
+            std::process::exit(1);
+        },
     };
 }
@@ -0,0 +1,264 @@
+use std::fmt::Write;
+
+use crate::{args::{DisplayMode, Preview}, driver::{panel::Panel, DisplayImage, Ink}};
+
+
+const RESET: &str = "\x1b[0m";
+
+
+/// Print the image the way the panel would show it in the mode
+pub fn preview(img: &DisplayImage, panel: &Panel, mode: DisplayMode, style: Preview) -> String {
+    let inks = Inks::new(img, panel, mode);
+
+    match style {
+        Preview::Terminal => terminal(&inks),
+        Preview::Blocks => blocks(&inks, mode),
+        Preview::Braille => braille(&inks),
+        Preview::Sixel => sixel(&inks),
+    }
+}
+
+
+/// The image reduced to what the panel can show
+struct Inks {
+    width: u32,
+    height: u32,
+    inks: Vec<Ink>,
+}
+
+impl Inks {
+    fn new(img: &DisplayImage, panel: &Panel, mode: DisplayMode) -> Self {
+        Self {
+            width: img.width(),
+            height: img.height(),
+            inks: img.pixels().map(|pixel| Ink::new(panel, mode, pixel)).collect(),
+        }
+    }
+
+    /// Outside of the image is white, like the panel around it
+    fn get(&self, x: u32, y: u32) -> Ink {
+        if x < self.width && y < self.height {
+            self.inks[(x + y * self.width) as usize]
+        } else {
+            Ink::White
+        }
+    }
+}
+
+
+fn rgb(ink: Ink) -> (u8, u8, u8) {
+    match ink {
+        Ink::Black => (0x00, 0x00, 0x00),
+        Ink::Dark => (0x55, 0x55, 0x55),
+        Ink::Light => (0xAA, 0xAA, 0xAA),
+        Ink::White => (0xFF, 0xFF, 0xFF),
+        Ink::Accent => (0xC0, 0x10, 0x10),
+    }
+}
+
+
+/// Upper half blocks with 24 bit colours: top pixel as foreground, bottom pixel as background
+fn terminal(inks: &Inks) -> String {
+    let mut out = String::new();
+
+    for y in (0..inks.height).step_by(2) {
+        for x in 0..inks.width {
+            let (tr, tg, tb) = rgb(inks.get(x, y));
+            let (br, bg, bb) = rgb(inks.get(x, y + 1));
+            write!(out, "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀", tr, tg, tb, br, bg, bb).unwrap();
+        }
+        writeln!(out, "{}", RESET).unwrap();
+    }
+
+    out
+}
+
+
+/// Plain characters without colours, light ink is drawn as a space
+fn blocks(inks: &Inks, mode: DisplayMode) -> String {
+    let mut out = String::new();
+
+    if let DisplayMode::Grey = mode {
+        // One character per pixel, so that every grey is exact
+        for y in 0..inks.height {
+            for x in 0..inks.width {
+                out.push(match inks.get(x, y) {
+                    Ink::Black | Ink::Accent => '█',
+                    Ink::Dark => '▓',
+                    Ink::Light => '░',
+                    Ink::White => ' ',
+                });
+            }
+            out.push('\n');
+        }
+    } else {
+        // Two pixels on top of each other per character
+        for y in (0..inks.height).step_by(2) {
+            for x in 0..inks.width {
+                let top = inks.get(x, y) != Ink::White;
+                let bottom = inks.get(x, y + 1) != Ink::White;
+                out.push(match (top, bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+
+/// 2x4 pixels per character, any ink counts as a dot
+fn braille(inks: &Inks) -> String {
+    // Bits of the dots in the Braille pattern block, by (x, y) within the cell
+    const DOTS: [[u32; 2]; 4] = [
+        [0x01, 0x08],
+        [0x02, 0x10],
+        [0x04, 0x20],
+        [0x40, 0x80],
+    ];
+
+    let mut out = String::new();
+
+    for y in (0..inks.height).step_by(4) {
+        for x in (0..inks.width).step_by(2) {
+            let mut bits = 0;
+            for (dy, row) in DOTS.iter().enumerate() {
+                for (dx, bit) in row.iter().enumerate() {
+                    if inks.get(x + dx as u32, y + dy as u32) != Ink::White {
+                        bits |= bit;
+                    }
+                }
+            }
+            out.push(char::from_u32(0x2800 + bits).unwrap());
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+
+/// DEC sixel graphics, shown by terminals like xterm, foot or WezTerm
+fn sixel(inks: &Inks) -> String {
+    const PALETTE: [Ink; 5] = [Ink::Black, Ink::Dark, Ink::Light, Ink::White, Ink::Accent];
+
+    let mut out = String::new();
+
+    // Start, 1:1 pixel aspect ratio and the size
+    write!(out, "\x1bPq\"1;1;{};{}", inks.width, inks.height).unwrap();
+
+    for (i, ink) in PALETTE.iter().enumerate() {
+        let (r, g, b) = rgb(*ink);
+        let percent = |c: u8| c as u32 * 100 / 255;
+        write!(out, "#{};2;{};{};{}", i, percent(r), percent(g), percent(b)).unwrap();
+    }
+
+    // Every band is 6 pixels tall, drawn once per colour
+    for band in (0..inks.height).step_by(6) {
+        for (i, ink) in PALETTE.iter().enumerate() {
+            write!(out, "#{}", i).unwrap();
+
+            let mut run: Option<(char, u32)> = None;
+            for x in 0..inks.width {
+                let mut bits = 0;
+                for dy in 0..6 {
+                    if band + dy < inks.height && inks.get(x, band + dy) == *ink {
+                        bits |= 1 << dy;
+                    }
+                }
+                let c = char::from(63 + bits);
+
+                run = match run {
+                    Some((r, n)) if r == c => Some((r, n + 1)),
+                    Some((r, n)) => {
+                        push_run(&mut out, r, n);
+                        Some((c, 1))
+                    },
+                    None => Some((c, 1)),
+                };
+            }
+            if let Some((r, n)) = run {
+                push_run(&mut out, r, n);
+            }
+
+            // Back to the start of the band for the next colour
+            out.push('$');
+        }
+
+        // Next band
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\\n");
+
+    out
+}
+
+
+fn push_run(out: &mut String, c: char, n: u32) {
+    if n > 3 {
+        write!(out, "!{}{}", n, c).unwrap();
+    } else {
+        for _ in 0..n {
+            out.push(c);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::driver::{panel::{EPD_2IN7_V2, EPD_4IN2B_V2}, ACCENT, BLACK, WHITE};
+
+    use super::*;
+
+    /// 4x4: a black, a dark grey, a light grey and a red pixel on the top row, white below
+    fn image() -> DisplayImage {
+        let mut img = DisplayImage::from_pixel(4, 4, WHITE);
+        img.put_pixel(0, 0, BLACK);
+        img.put_pixel(1, 0, image::Rgb([0x60, 0x60, 0x60]));
+        img.put_pixel(2, 0, image::Rgb([0xB0, 0xB0, 0xB0]));
+        img.put_pixel(3, 0, ACCENT);
+        img
+    }
+
+    #[test]
+    fn blocks() {
+        let img = image();
+
+        assert_eq!(preview(&img, &EPD_2IN7_V2, DisplayMode::Fast, Preview::Blocks), "▀▀ ▀\n    \n");
+        assert_eq!(preview(&img, &EPD_2IN7_V2, DisplayMode::Grey, Preview::Blocks), "█▓░█\n    \n    \n    \n");
+    }
+
+    #[test]
+    fn braille() {
+        let img = image();
+
+        // Light grey is white in the Fast mode, red is black without the second ink
+        assert_eq!(preview(&img, &EPD_2IN7_V2, DisplayMode::Fast, Preview::Braille), "⠉⠈\n");
+    }
+
+    #[test]
+    fn terminal() {
+        let img = image();
+        let out = preview(&img, &EPD_4IN2B_V2, DisplayMode::Full, Preview::Terminal);
+
+        // Red stays red on panels with the second ink, the odd row below is white
+        assert!(out.starts_with("\x1b[38;2;0;0;0m\x1b[48;2;255;255;255m▀"));
+        assert!(out.contains("\x1b[38;2;192;16;16m\x1b[48;2;255;255;255m▀"));
+        assert_eq!(out.lines().count(), 2);
+    }
+
+    #[test]
+    fn sixel() {
+        let out = preview(&image(), &EPD_2IN7_V2, DisplayMode::Grey, Preview::Sixel);
+
+        assert!(out.starts_with("\x1bPq\"1;1;4;4"));
+        assert!(out.ends_with("\x1b\\\n"));
+    }
+}
@@ -2,17 +2,41 @@ use std::sync::Mutex;
 
 use lazy_static::lazy_static;
 use cosmic_text::{Attrs, Buffer, Color, Family, FontSystem, Metrics, Shaping, SwashCache};
-use image::Luma;
+use image::{ImageBuffer, Luma, Rgb};
 use imageproc::{drawing::{draw_antialiased_line_segment_mut, draw_filled_rect_mut, draw_hollow_rect_mut}, pixelops::interpolate, rect::Rect};
 
-use crate::driver::{DisplayImage, DisplayImagePixel};
+use crate::{args::Dither, driver::{DisplayImage, DisplayImagePixel}};
+
+/// Colour of regular text
+pub const TEXT: Color = Color::rgb(0, 0, 0);
+/// Colour of text that should stand out, see `driver::ACCENT`
+pub const TEXT_ACCENT: Color = Color::rgb(0xFF, 0, 0);
+/// Colour of text on black
+pub const TEXT_INVERTED: Color = Color::rgb(0xFF, 0xFF, 0xFF);
 
 lazy_static! {
-    static ref FONT_SYSTEM: Mutex<FontSystem> = Mutex::new(FontSystem::new());
+    static ref FONT_SYSTEM: Mutex<FontSystem> = Mutex::new(font_system());
     static ref SWASH_CACHE: Mutex<SwashCache> = Mutex::new(SwashCache::new());
 }
 
 
+#[cfg(not(test))]
+fn font_system() -> FontSystem {
+    FontSystem::new()
+}
+
+
+/// Tests only see the font in the fixtures, so golden images don't depend on the fonts installed
+#[cfg(test)]
+fn font_system() -> FontSystem {
+    let mut db = cosmic_text::fontdb::Database::new();
+    db.load_font_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/fonts/DejaVuSans.ttf"))
+        .expect("The fixture font should load");
+
+    FontSystem::new_with_locale_and_db("en-US".to_string(), db)
+}
+
+
 pub fn draw_line(img: &mut DisplayImage, color: DisplayImagePixel, start: (i32, i32), end: (i32, i32)) {
     draw_antialiased_line_segment_mut(img, start, end, color, interpolate);
 }
@@ -23,8 +47,64 @@ pub fn draw_box(img: &mut DisplayImage, color: DisplayImagePixel, rect: Rect) {
 }
 
 
+pub fn fill_box(img: &mut DisplayImage, color: DisplayImagePixel, rect: Rect) {
+    draw_filled_rect_mut(img, rect, color);
+}
+
+
 pub fn draw_text(img: &mut DisplayImage, color: Color, rect: Rect, text: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let metrics = Metrics::new(16.0, 18.0);
+    draw_rich_text(img, rect, &[(text, color)])
+}
+
+
+/// Outline of `rect` filled from the left by the `done` part of `total`
+pub fn draw_progress(img: &mut DisplayImage, color: DisplayImagePixel, rect: Rect, done: usize, total: usize) {
+    draw_box(img, color, rect);
+
+    let inner = rect.width().saturating_sub(2) as usize;
+    let width = (inner * done.min(total)).checked_div(total).unwrap_or(0) as u32;
+    if width > 0 && rect.height() > 2 {
+        fill_box(img, color, Rect::at(rect.left() + 1, rect.top() + 1).of_size(width, rect.height() - 2));
+    }
+}
+
+
+/// Draw text made of differently coloured spans
+pub fn draw_rich_text(img: &mut DisplayImage, rect: Rect, spans: &[(&str, Color)]) -> Result<(), Box<dyn std::error::Error>> {
+    draw_rich_text_sized(img, rect, spans, 16.0)
+}
+
+
+/// Draw text with the font of `size` pixels, centered horizontally in `rect`
+pub fn draw_text_centered(img: &mut DisplayImage, color: Color, rect: Rect, text: &str, size: f32) -> Result<(), Box<dyn std::error::Error>> {
+    let width = text_width(text, size)?;
+    let left = rect.left() + (rect.width() as i32 - width as i32).max(0) / 2;
+
+    let rect = Rect::at(left, rect.top()).of_size(rect.width() - (left - rect.left()) as u32, rect.height());
+    draw_rich_text_sized(img, rect, &[(text, color)], size)
+}
+
+
+/// Width of the widest line of the text in pixels
+pub fn text_width(text: &str, size: f32) -> Result<u32, Box<dyn std::error::Error>> {
+    let mut font_system = FONT_SYSTEM.lock()?;
+
+    let mut buffer = Buffer::new(&mut font_system, Metrics::new(size, size * 1.125));
+    let mut buffer = buffer.borrow_with(&mut font_system);
+
+    buffer.set_size(f32::MAX, f32::MAX);
+    buffer.set_text(text, Attrs::new().family(Family::Name("JetBrains Mono")), Shaping::Advanced);
+    buffer.shape_until_scroll(true);
+
+    let width = buffer.layout_runs().map(|run| run.line_w).fold(0.0, f32::max);
+
+    Ok(width.ceil() as u32)
+}
+
+
+/// Draw text made of differently coloured spans with the font of `size` pixels
+pub fn draw_rich_text_sized(img: &mut DisplayImage, rect: Rect, spans: &[(&str, Color)], size: f32) -> Result<(), Box<dyn std::error::Error>> {
+    let metrics = Metrics::new(size, size * 1.125);
 
     let mut font_system = FONT_SYSTEM.lock()?;
     let mut swash_cache = SWASH_CACHE.lock()?;
@@ -38,10 +118,11 @@ pub fn draw_text(img: &mut DisplayImage, color: Color, rect: Rect, text: &str) -
     let attrs = Attrs::new()
         .family(Family::Name("JetBrains Mono"));
 
-    buffer.set_text(text, attrs, Shaping::Advanced);
+    let spans = spans.iter().map(|(text, color)| (*text, attrs.color(*color)));
+    buffer.set_rich_text(spans, attrs, Shaping::Advanced);
     buffer.shape_until_scroll(true);
 
-    buffer.draw(&mut swash_cache, color, |x, y, w, h, color| {
+    buffer.draw(&mut swash_cache, TEXT, |x, y, w, h, color| {
         if w != 1 || h != 1 {
             // We don't like non-pixels (it's fiiine...)
             return;
@@ -51,25 +132,80 @@ pub fn draw_text(img: &mut DisplayImage, color: Color, rect: Rect, text: &str) -
         let x = x + rect.left();
         let y = y + rect.top();
 
-        if x < 0 || y < 0 || x as u32 > img.width() || y as u32 > img.height() {
+        if x < 0 || y < 0 || x as u32 >= img.width() || y as u32 >= img.height() {
             // Out of image bounds
             return;
         }
 
         // TODO: in theory, shaped text can escape the rectangle, this needs filtering out
 
-        // The input is RGBA, but the output is grayscale, so get the average color across R, G & B
-        let grey = (color.r() as f32 + color.g() as f32 + color.b() as f32) / 3.0;
         // Extract the alpha as [0, 1] float so it's easier to mix the colors
         let alpha = color.a() as f32 / 255.0;
+        let mix = |new: u8, current: u8| (new as f32 * alpha + current as f32 * (1.0 - alpha)) as u8;
 
-        let color_current = img.get_pixel(x as u32, y as u32).0[0] as f32;
-        let color_new = grey * alpha + color_current * (1.0 - alpha);
+        let Rgb([r, g, b]) = *img.get_pixel(x as u32, y as u32);
 
         let pixel = Rect::at(x, y).of_size(w, h);
-        let color = Luma([color_new as u8]);
+        let color = Rgb([mix(color.r(), r), mix(color.g(), g), mix(color.b(), b)]);
         draw_filled_rect_mut(img, pixel, color);
     });
 
     Ok(())
 }
+
+
+/// Greyscale picture with brightness in [0, 1]
+pub type Picture = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+
+/// Draw the picture with its top left corner at `at`, reducing it to `levels` evenly spaced greys
+pub fn draw_picture(img: &mut DisplayImage, picture: &Picture, at: (i32, i32), levels: u8, dither: Dither) {
+    const BAYER: [[f32; 4]; 4] = [
+        [ 0.0,  8.0,  2.0, 10.0],
+        [12.0,  4.0, 14.0,  6.0],
+        [ 3.0, 11.0,  1.0,  9.0],
+        [15.0,  7.0, 13.0,  5.0],
+    ];
+
+    let steps = (levels.max(2) - 1) as f32;
+    let quantize = |v: f32| (v.clamp(0.0, 1.0) * steps).round() / steps;
+
+    // Values with the error spread from the already drawn neighbours
+    let mut values = picture.clone();
+
+    for y in 0..picture.height() {
+        for x in 0..picture.width() {
+            let value = values.get_pixel(x, y).0[0];
+
+            let level = match dither {
+                Dither::None | Dither::FloydSteinberg => quantize(value),
+                Dither::Bayer => {
+                    let threshold = (BAYER[y as usize % 4][x as usize % 4] + 0.5) / 16.0 - 0.5;
+                    quantize(value + threshold / steps)
+                },
+            };
+
+            if let Dither::FloydSteinberg = dither {
+                let error = value - level;
+                let mut spread = |dx: i32, dy: u32, weight: f32| {
+                    let (nx, ny) = (x as i32 + dx, y + dy);
+                    if nx >= 0 && (nx as u32) < picture.width() && ny < picture.height() {
+                        values.get_pixel_mut(nx as u32, ny).0[0] += error * weight;
+                    }
+                };
+                spread(1, 0, 7.0 / 16.0);
+                spread(-1, 1, 3.0 / 16.0);
+                spread(0, 1, 5.0 / 16.0);
+                spread(1, 1, 1.0 / 16.0);
+            }
+
+            let (ix, iy) = (x as i32 + at.0, y as i32 + at.1);
+            if ix < 0 || iy < 0 || ix as u32 >= img.width() || iy as u32 >= img.height() {
+                continue;
+            }
+
+            let grey = (level * u8::MAX as f32).round() as u8;
+            img.put_pixel(ix as u32, iy as u32, Rgb([grey, grey, grey]));
+        }
+    }
+}
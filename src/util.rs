@@ -1,11 +1,64 @@
+use std::path::Path;
 use std::thread;
 use std::time::Duration;
 
 use log::debug;
 
+use crate::app_error::AppError;
+
 
 pub fn sleep_ms(ms: u64) {
     debug!("Sleeping for {} ms", ms);
     thread::sleep(Duration::from_millis(ms));
     debug!("Done sleeping for {} ms", ms);
 }
+
+
+/// Read a temperature in °C from a sensor file.
+///
+/// hwmon (`temp1_input`) and IIO (`in_temp_input`) files end with `_input` and report millidegrees,
+/// other files are read as degrees.
+pub fn read_temperature(path: &Path) -> Result<f32, AppError> {
+    let s = std::fs::read_to_string(path)?;
+    let value: f32 = s.trim().parse().map_err(|e| AppError::Data(
+        format!("Wrong temperature '{}' in {}: {}", s.trim(), path.display(), e)
+    ))?;
+
+    let millidegrees = path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.ends_with("_input"));
+
+    if millidegrees {
+        Ok(value / 1000.0)
+    } else {
+        Ok(value)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temperatures() {
+        let dir = std::env::temp_dir().join(format!("pinterface-temperature-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let read = |name: &str, s: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, s).unwrap();
+            read_temperature(&path).ok()
+        };
+
+        assert_eq!(read("temperature", "21.5\n"), Some(21.5));
+        assert_eq!(read("temperature", "-200"), Some(-200.0));
+        assert_eq!(read("temperature", "warm"), None);
+
+        // Millidegrees, also close to 0 °C
+        assert_eq!(read("temp1_input", "23125\n"), Some(23.125));
+        assert_eq!(read("temp1_input", "-5000"), Some(-5.0));
+        assert_eq!(read("in_temp_input", "150"), Some(0.15));
+        assert_eq!(read("in_temp_input", "-120\n"), Some(-0.12));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,15 +1,21 @@
-use chrono::{Duration, NaiveTime};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
 use lazy_static::lazy_static;
-use markdown::mdast::{ListItem, Node, Paragraph};
+use log::debug;
+use markdown::{mdast::{ListItem, Node, Paragraph}, Constructs, ParseOptions};
 use regex::Regex;
 use serde::Deserialize;
 
 use crate::app_error::AppError;
 
 
+/// Settings of the periodic notes plugin, saying where the daily notes are
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct PluginConfig {
+pub struct PluginConfig {
     #[serde(default)]
     pub group_by_year: bool,
     #[serde(default)]
@@ -17,90 +23,652 @@ struct PluginConfig {
 }
 
 
-pub fn read_today_note() -> Result<String, Box<dyn std::error::Error>> {
-    let vault = std::path::PathBuf::from(std::env::var("VAULT")?);
-    let now = chrono::Local::now();
+/// Obsidian vault with periodic notes
+pub struct Vault {
+    root: Option<PathBuf>,
+}
+
+impl Vault {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: Some(root.into()) }
+    }
+
+    /// The vault at `VAULT`, failing on use if it's not set
+    pub fn from_env() -> Self {
+        match std::env::var_os("VAULT") {
+            Some(root) => Self::new(root),
+            None => Self { root: None },
+        }
+    }
+
+    fn root(&self) -> Result<&PathBuf, AppError> {
+        self.root.as_ref().ok_or(AppError::Vault { path: None, message: "VAULT is not set".to_string() })
+    }
+
+    /// Error about a file in the vault, with the path relative to it
+    fn file_error(&self, path: &Path, message: String) -> AppError {
+        let path = self.root.as_ref().and_then(|root| path.strip_prefix(root).ok()).unwrap_or(path);
+        AppError::Vault { path: Some(path.to_path_buf()), message }
+    }
+
+    pub fn config(&self) -> Result<PluginConfig, Box<dyn std::error::Error>> {
+        let config_path = self.root()?.join(".obsidian/plugins/ftvkyo/data.json");
+        let config_s = std::fs::read_to_string(&config_path).map_err(|e| self.file_error(&config_path, e.to_string()))?;
+        let config: PluginConfig = serde_json::from_str(&config_s)?;
+
+        Ok(config)
+    }
+
+    /// Where the daily note for the date is, whether it exists or not
+    pub fn note_path(&self, date: NaiveDate) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        self.note_path_in(&self.config()?, date)
+    }
+
+    /// `note_path` with the plugin settings read already, for going over many days
+    fn note_path_in(&self, config: &PluginConfig, date: NaiveDate) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let note_pattern = if config.group_by_year {
+            "%Y/%Y%m%d.md"
+        } else {
+            "%Y%m%d.md"
+        };
+
+        let note_filename = date.format(note_pattern).to_string();
+        let note_path = self.root()?.join(&config.folder_periodic).join(note_filename);
+
+        Ok(note_path)
+    }
+
+    pub fn read_note(&self, date: NaiveDate) -> Result<String, Box<dyn std::error::Error>> {
+        self.read_note_at(&self.note_path(date)?, date)
+    }
+
+    fn read_note_at(&self, note_path: &Path, date: NaiveDate) -> Result<String, Box<dyn std::error::Error>> {
+        let note_s = std::fs::read_to_string(note_path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => self.file_error(note_path, format!("No note for {}", date)),
+            _ => self.file_error(note_path, e.to_string()),
+        })?;
+
+        Ok(note_s)
+    }
+
+    /// When the vault last changed and the notes Obsidian Sync could not merge, from one walk over its files
+    pub fn changes(&self) -> Result<Changes, Box<dyn std::error::Error>> {
+        let root = self.root()?;
+        let mut changes = Changes::default();
+
+        for (path, metadata) in self.files()? {
+            changes.modified = changes.modified.max(Some(metadata.modified()?));
+
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if name.contains(" (conflict ") && name.ends_with(".md") {
+                changes.conflicts.push(path.strip_prefix(root).map(Path::to_path_buf).unwrap_or_else(|_| path.clone()));
+            }
+        }
+        changes.conflicts.sort();
+
+        Ok(changes)
+    }
+
+    /// Every file of the vault, hidden folders like `.obsidian` left out
+    fn files(&self) -> Result<Vec<(PathBuf, std::fs::Metadata)>, Box<dyn std::error::Error>> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.root()?.clone()];
+
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let entry = entry?;
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+
+                let metadata = match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    // Removed by the sync since the folder was listed
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e.into()),
+                };
+
+                if metadata.is_dir() {
+                    dirs.push(entry.path());
+                } else {
+                    files.push((entry.path(), metadata));
+                }
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Daily note parsed into its properties and tasks, `None` when there is none, for going over many days
+    pub fn note_in(&self, config: &PluginConfig, date: NaiveDate) -> Result<Option<Note>, Box<dyn std::error::Error>> {
+        let note_path = self.note_path_in(config, date)?;
+        let note_s = match std::fs::read_to_string(&note_path) {
+            Ok(note_s) => note_s,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(self.file_error(&note_path, e.to_string()).into()),
+        };
+
+        Ok(Some(Note::parse(&note_s)?))
+    }
+
+    /// Add a line at the end of the daily note, creating the note if there is none yet
+    pub fn append_note(&self, date: NaiveDate, line: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let note_path = self.note_path(date)?;
+        let note_s = std::fs::read_to_string(&note_path).unwrap_or_default();
+
+        // The folder of a new month or year may not be there yet
+        if let Some(folder) = note_path.parent() {
+            std::fs::create_dir_all(folder)?;
+        }
+
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&note_path)?;
+
+        // Don't glue the line to the last one
+        if !note_s.is_empty() && !note_s.ends_with('\n') {
+            writeln!(file)?;
+        }
+        writeln!(file, "{}", line)?;
+
+        Ok(())
+    }
+
+    /// Track the habits over the daily notes of `days` days up to `last`, missing notes count as not done
+    pub fn habits(&self, names: &[String], last: NaiveDate, days: u32) -> Result<Vec<Habit>, Box<dyn std::error::Error>> {
+        let mut habits: Vec<_> = names.iter()
+            .map(|name| Habit { name: name.clone(), values: Vec::new() })
+            .collect();
+
+        let config = self.config()?;
+
+        for days_ago in (0..days).rev() {
+            let date = last - Duration::days(days_ago as i64);
+
+            let note_path = self.note_path_in(&config, date)?;
+            let note = if note_path.exists() {
+                self.read_note_at(&note_path, date)?
+            } else {
+                String::new()
+            };
+
+            let fields = inline_fields(&note);
+            let tasks = collect_tasks(&note_to_ast(&note), &note)?;
+
+            for habit in &mut habits {
+                habit.values.push(habit_value(&habit.name, &fields, &tasks));
+            }
+        }
+
+        Ok(habits)
+    }
+
+    /// How many tasks of the daily note are done, `None` when there is no note
+    pub fn task_counts(&self, config: &PluginConfig, date: NaiveDate) -> Result<Option<TaskCounts>, Box<dyn std::error::Error>> {
+        let note_path = self.note_path_in(config, date)?;
+        if !note_path.exists() {
+            return Ok(None);
+        }
+
+        let note = Note::parse(&self.read_note_at(&note_path, date)?)?;
+        Ok(Some(TaskCounts::of(&note.tasks)))
+    }
+}
+
+
+/// What a walk over the vault found, see `Vault::changes`
+#[derive(Debug, Default)]
+pub struct Changes {
+    /// When a file was last changed, `None` in an empty vault
+    pub modified: Option<SystemTime>,
+    /// Conflict files like `Note (conflict 2026-10-18 09-20).md`, relative to the vault
+    pub conflicts: Vec<PathBuf>,
+}
+
+
+pub fn note_to_ast(note: &str) -> Node {
+    // Frontmatter would be taken for a horizontal rule and a heading otherwise,
+    // but the parser panics on one that is not closed
+    let options = ParseOptions {
+        constructs: Constructs { frontmatter: frontmatter(note).is_some(), ..Constructs::gfm() },
+        ..ParseOptions::gfm()
+    };
+
+    markdown::to_mdast(note, &options)
+        .expect("Markdown can't have syntax errors")
+}
+
+
+/// Blocks under a heading of the note
+#[derive(Debug)]
+pub struct Section<'n> {
+    /// The heading as it is written
+    pub title: String,
+    pub blocks: &'n [Node],
+}
+
+impl Section<'_> {
+    /// Tasks in the section, checked or not
+    pub fn tasks(&self, original: &str) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+        let mut tasks = Vec::new();
+        for block in self.blocks {
+            tasks.extend(collect_tasks(block, original)?);
+        }
+
+        Ok(tasks)
+    }
+}
+
 
-    let config_path = vault.join(".obsidian/plugins/ftvkyo/data.json");
-    let config_s = std::fs::read_to_string(config_path)?;
-    let config: PluginConfig = serde_json::from_str(&config_s)?;
+/// Find the section under the heading at `path`, e.g. `Plan > Morning`, ignoring case and skipped levels.
+/// It ends at the next heading of the same or a higher level.
+pub fn section<'n>(ast: &'n Node, path: &str) -> Option<Section<'n>> {
+    let wanted = heading_path(path);
+    let children = ast.children()?;
+
+    // Headings the current one is nested in, with itself at the end
+    let mut parents: Vec<(u8, String)> = Vec::new();
+
+    for (i, child) in children.iter().enumerate() {
+        let Node::Heading(heading) = child else {
+            continue;
+        };
+
+        parents.retain(|(depth, _)| *depth < heading.depth);
+        parents.push((heading.depth, child.to_string().trim().to_lowercase()));
+
+        if !ends_with_path(&parents, &wanted) {
+            continue;
+        }
 
-    let note_pattern = if config.group_by_year {
-        "%Y/%Y%m%d.md"
-    } else {
-        "%Y%m%d.md"
+        let rest = &children[i + 1..];
+        let end = rest.iter()
+            .position(|node| matches!(node, Node::Heading(next) if next.depth <= heading.depth))
+            .unwrap_or(rest.len());
+
+        return Some(Section {
+            title: child.to_string().trim().to_string(),
+            blocks: &rest[..end],
+        });
+    }
+
+    None
+}
+
+
+/// Lowercase names of the headings in a path like `Plan > Morning`, the `#`s are optional
+fn heading_path(path: &str) -> Vec<String> {
+    path.split('>')
+        .map(|name| name.trim().trim_start_matches('#').trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+
+fn ends_with_path(parents: &[(u8, String)], wanted: &[String]) -> bool {
+    let (Some((last, wanted)), Some(((_, heading), parents))) = (wanted.split_last(), parents.split_last()) else {
+        return false;
     };
 
-    let note_filename = now.format(note_pattern).to_string();
-    let note_path = vault.join(config.folder_periodic).join(note_filename);
-    let note_s = std::fs::read_to_string(&note_path)?;
+    let mut parents = parents.iter().rev();
+    heading == last && wanted.iter().rev().all(|name| parents.any(|(_, heading)| heading == name))
+}
+
+
+/// Daily note with its properties
+#[derive(Debug)]
+pub struct Note {
+    pub properties: Vec<(String, Property)>,
+    pub tasks: Vec<Task>,
+}
+
+impl Note {
+    pub fn parse(note: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            properties: frontmatter(note).map(parse_properties).unwrap_or_default(),
+            tasks: collect_tasks(&note_to_ast(note), note)?,
+        })
+    }
+
+    /// Value of the property, ignoring the case of the name
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name.trim()))
+            .map(|(_, value)| value)
+    }
+}
+
+
+/// Value of a property in the frontmatter, typed as Obsidian does
+#[derive(Clone, Debug, PartialEq)]
+pub enum Property {
+    Empty,
+    Text(String),
+    Number(f64),
+    Checkbox(bool),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    List(Vec<String>),
+}
+
+impl Property {
+    fn parse(s: &str) -> Self {
+        let s = s.trim();
+
+        if let Some(text) = unquote(s) {
+            return Self::Text(text);
+        }
+
+        // Comments are only allowed outside of quotes
+        let s = s.split_once(" #").map_or(s, |(s, _)| s).trim();
+
+        if s.is_empty() || s == "null" || s == "~" {
+            return Self::Empty;
+        }
+
+        if let Some(items) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            return Self::List(items.split(',')
+                .map(|item| item.trim())
+                .filter(|item| !item.is_empty())
+                .map(|item| unquote(item).unwrap_or(item.to_string()))
+                .collect());
+        }
+
+        match s {
+            "true" | "True" => return Self::Checkbox(true),
+            "false" | "False" => return Self::Checkbox(false),
+            _ => {},
+        }
+
+        if let Ok(n) = s.parse() {
+            return Self::Number(n);
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return Self::Date(date);
+        }
+        for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+            if let Ok(time) = NaiveDateTime::parse_from_str(s, format) {
+                return Self::DateTime(time);
+            }
+        }
+
+        Self::Text(s.to_string())
+    }
+}
+
+impl std::fmt::Display for Property {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => Ok(()),
+            Self::Text(text) => write!(f, "{}", text),
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Checkbox(true) => write!(f, "yes"),
+            Self::Checkbox(false) => write!(f, "no"),
+            Self::Date(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+            Self::DateTime(time) => write!(f, "{}", time.format("%Y-%m-%d %H:%M")),
+            Self::List(items) => write!(f, "{}", items.join(", ")),
+        }
+    }
+}
+
+
+/// `"text"` or `'text'` without the quotes
+fn unquote(s: &str) -> Option<String> {
+    if let Some(s) = s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        return Some(s.replace("\\\"", "\"").replace("\\\\", "\\"));
+    }
+    s.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')).map(|s| s.replace("''", "'"))
+}
+
+
+/// YAML between the `---` lines at the very top of the note
+fn frontmatter(note: &str) -> Option<&str> {
+    let rest = note.strip_prefix("---\n").or_else(|| note.strip_prefix("---\r\n"))?;
+
+    let mut end = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some(&rest[..end]);
+        }
+        end += line.len();
+    }
 
-    return Ok(note_s);
+    None
 }
 
 
-pub fn note_to_ast(note: &String) -> Node {
-    return markdown::to_mdast(&note, &markdown::ParseOptions::gfm())
-        .expect("Markdown can't have syntax errors");
+/// Properties as Obsidian writes them: `key: value`, with lists as `[a, b]` or `- a` lines below the key
+fn parse_properties(yaml: &str) -> Vec<(String, Property)> {
+    let mut properties: Vec<(String, Property)> = Vec::new();
+
+    for line in yaml.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with([' ', '\t', '-']) {
+            let Some(item) = trimmed.strip_prefix('-') else {
+                debug!("Ignoring nested YAML: {}", line);
+                continue;
+            };
+
+            let item = Property::parse(item).to_string();
+            match properties.last_mut() {
+                Some((_, Property::List(items))) => items.push(item),
+                Some((_, value @ Property::Empty)) => *value = Property::List(vec![item]),
+                _ => debug!("List item without a key: {}", line),
+            }
+            continue;
+        }
+
+        match line.split_once(':') {
+            Some((key, value)) => properties.push((unquote(key.trim()).unwrap_or(key.trim().to_string()), Property::parse(value))),
+            None => debug!("Not a property: {}", line),
+        }
+    }
+
+    properties
 }
 
 
 lazy_static! {
-    static ref RE_TIME: Regex = Regex::new(r"\[time::\s*(?<h>\d{1,2}):(?<m>\d{2})\s+(?:(?<dh>\d+)h)?(?:(?<dm>\d+)m)?\s*\]").unwrap();
+    static ref RE_STATUS: Regex = Regex::new(r"^\[(?<mark>[^\[\]])\](?:[ \t]+|$)").unwrap();
+    static ref RE_TIME: Regex = Regex::new(r"\[time::\s*(?<h>\d{1,2}):(?<m>\d{2})\s*(?:(?<dh>\d+)h)?(?:(?<dm>\d+)m)?\s*\]").unwrap();
+
+    /// Dataview field taking a whole line, possibly in a list item: `water:: 6`
+    static ref RE_FIELD_LINE: Regex = Regex::new(r"(?m)^[ \t]*(?:[-*+][ \t]+(?:\[.\][ \t]+)?)?(?<key>[^\[\]():\n]+?)::[ \t]*(?<value>.*?)[ \t]*$").unwrap();
+
+    /// Dataview field inside text: `[water:: 6]` or `(water:: 6)`
+    static ref RE_FIELD_INLINE: Regex = Regex::new(r"[\[(](?<key>[^\[\]():\n]+?)::\s*(?<value>[^\])\n]*?)\s*[\])]").unwrap();
+}
+
+
+/// How much of the habit was done: the largest value of its fields, or 1 for a checked task named after it
+fn habit_value(name: &str, fields: &[(String, String)], tasks: &[Task]) -> f32 {
+    let name = name.trim().to_lowercase();
+
+    let field = fields.iter()
+        .filter(|(key, _)| *key == name)
+        .map(|(_, value)| match value.to_lowercase().as_str() {
+            "true" | "yes" | "done" | "x" | "✓" | "✔" => 1.0,
+            value => value.parse::<f32>().unwrap_or(0.0).max(0.0),
+        })
+        .fold(0.0, f32::max);
+
+    if is_checked(tasks, &name) { field.max(1.0) } else { field }
+}
+
+
+fn is_checked(tasks: &[Task], name: &str) -> bool {
+    tasks.iter().any(|task| {
+        (task.status == Status::Done && task.text.trim().to_lowercase() == name) || is_checked(&task.subtasks, name)
+    })
+}
+
+
+/// Dataview inline fields of the note as (key, value), keys in lower case
+pub fn inline_fields(note: &str) -> Vec<(String, String)> {
+    RE_FIELD_LINE.captures_iter(note)
+        .chain(RE_FIELD_INLINE.captures_iter(note))
+        .map(|caps| (caps["key"].trim().to_lowercase(), caps["value"].to_string()))
+        .collect()
+}
+
+
+/// Values of a habit by day, oldest first, 0 when not done
+#[derive(Debug)]
+pub struct Habit {
+    pub name: String,
+    pub values: Vec<f32>,
+}
+
+impl Habit {
+    /// Days in a row the habit was done up to today, today doesn't break it until it's over
+    pub fn streak(&self) -> usize {
+        let done = |values: &[f32]| values.iter().rev().take_while(|v| **v > 0.0).count();
+
+        match self.values.split_last() {
+            Some((today, before)) if *today <= 0.0 => done(before),
+            _ => done(&self.values),
+        }
+    }
+}
+
+
+/// Done and all tasks, subtasks included, deferred and cancelled ones left out
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TaskCounts {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl TaskCounts {
+    pub fn of(tasks: &[Task]) -> Self {
+        tasks.iter().fold(Self::default(), |counts, task| {
+            let subtasks = Self::of(&task.subtasks);
+            let counted = task.status.is_open() || task.status == Status::Done;
+            Self {
+                done: counts.done + subtasks.done + (task.status == Status::Done) as usize,
+                total: counts.total + subtasks.total + counted as usize,
+            }
+        })
+    }
+
+    pub fn open(&self) -> usize {
+        self.total - self.done
+    }
 }
 
 
 #[derive(Debug)]
 pub struct Task {
     pub text: String,
-    pub checked: bool,
+    pub status: Status,
     pub subtasks: Vec<Task>,
     pub time: Option<(NaiveTime, Duration)>,
+    /// Headings the task is under, outermost first
+    pub headings: Vec<String>,
+    /// Text of the plain list item the task is under
+    pub parent: Option<String>,
+}
+
+impl Task {
+    /// Whether the task is under the headings at `path`, e.g. `Plan > Morning`, ignoring case and skipped levels
+    pub fn is_under(&self, path: &str) -> bool {
+        let wanted = heading_path(path);
+        let mut headings = self.headings.iter();
+        !wanted.is_empty() && wanted.iter().all(|name| headings.any(|heading| heading.to_lowercase() == *name))
+    }
+
+    /// When the task ends if it has a time, for a task of the note of `date`
+    pub fn ends(&self, date: NaiveDate) -> Option<NaiveDateTime> {
+        self.time.map(|(start, duration)| date.and_time(start) + duration)
+    }
+}
+
+
+/// State of a task by the character in its checkbox, as the Tasks plugin and many themes use them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// `[ ]`
+    Todo,
+    /// `[/]`
+    InProgress,
+    /// `[!]`
+    Important,
+    /// `[?]`
+    Question,
+    /// `[>]`, moved to another day
+    Deferred,
+    /// `[-]`
+    Cancelled,
+    /// `[x]`, and any other character as Obsidian shows them checked
+    Done,
+}
+
+impl Status {
+    fn from_mark(mark: char) -> Self {
+        match mark {
+            ' ' => Self::Todo,
+            '/' => Self::InProgress,
+            '!' => Self::Important,
+            '?' => Self::Question,
+            '>' => Self::Deferred,
+            '-' => Self::Cancelled,
+            _ => Self::Done,
+        }
+    }
+
+    /// Character in the checkbox
+    pub fn mark(&self) -> char {
+        match self {
+            Self::Todo => ' ',
+            Self::InProgress => '/',
+            Self::Important => '!',
+            Self::Question => '?',
+            Self::Deferred => '>',
+            Self::Cancelled => '-',
+            Self::Done => 'x',
+        }
+    }
+
+    /// Symbol the views put before the task
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            Self::Todo => "-",
+            Self::InProgress => "◐",
+            Self::Important => "!",
+            Self::Question => "?",
+            Self::Deferred => "→",
+            Self::Cancelled => "✗",
+            Self::Done => "✓",
+        }
+    }
+
+    /// Whether something is still to be done about the task
+    pub fn is_open(&self) -> bool {
+        matches!(self, Self::Todo | Self::InProgress | Self::Important | Self::Question)
+    }
+}
+
+
+/// All tasks under the node, checked or not; see `unchecked` to leave out the done ones
+pub fn collect_tasks(node: &Node, original: &str) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+    collect(node, original, &mut Vec::new(), None)
 }
 
 
-pub fn collect_tasks(node: &Node, original: &str, only_unchecked: bool) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+/// Collect tasks, keeping track of the headings met so far and the plain list item they are in
+fn collect(node: &Node, original: &str, headings: &mut Vec<(u8, String)>, parent: Option<&str>) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
     // Expecting the top of recursion to not be a list item node :)
 
     let mut tasks = Vec::new();
 
     if let Some(children) = node.children() {
         for child in children {
-            if let (
-                Node::ListItem(li),
-                Node::ListItem(ListItem {
-                    checked: Some(checked),
-                    ..
-                })
-            ) = (child, child) {
+            if let Some((status, mut text)) = as_task(child, original) {
                 // Then this is a task
 
-                if only_unchecked && *checked {
-                    // Skip tasks that are checked if we only care about unchecked tasks
-                    continue;
-                }
-
-                // Extract the text contents of the list item
-                let mut text = String::new();
-                for li_child in &li.children {
-                    match li_child {
-                        Node::Paragraph(
-                            Paragraph {
-                                position: Some(position),
-                                ..
-                            }
-                        ) => {
-                            let p_text = &original[position.start.offset..position.end.offset];
-                            text.push_str(p_text);
-                        },
-                        _ => {
-                            // Only extract paragraphs in the beginning.
-                            break;
-                        }
-                    }
-                }
-
-                let subtasks = collect_tasks(child, original, only_unchecked)?;
+                let subtasks = collect(child, original, headings, parent)?;
 
                 let time = if let Some(caps) = RE_TIME.captures(&text) {
                     let all = caps.get(0).unwrap();
@@ -127,17 +695,438 @@ pub fn collect_tasks(node: &Node, original: &str, only_unchecked: bool) -> Resul
 
                 tasks.push(Task {
                     text,
-                    checked: *checked,
+                    status,
                     subtasks,
                     time,
+                    headings: headings.iter().map(|(_, heading)| heading.clone()).collect(),
+                    parent: parent.map(str::to_string),
                 })
+            } else if let Node::Heading(heading) = child {
+                headings.retain(|(depth, _)| *depth < heading.depth);
+                headings.push((heading.depth, child.to_string().trim().to_string()));
+            } else if let Node::ListItem(li) = child {
+                // A plain list item, the tasks in it are grouped under its text
+
+                let text = item_text(li, original);
+                tasks.extend(collect(child, original, headings, Some(text.trim()).filter(|t| !t.is_empty()).or(parent))?);
             } else {
                 // Then this is not a task and we should propagate children tasks
 
-                tasks.extend(collect_tasks(child, original, only_unchecked)?);
+                tasks.extend(collect(child, original, headings, parent)?);
             }
         }
     }
 
     Ok(tasks)
 }
+
+
+/// Status and text of the list item if it's a task, custom statuses like `- [/]` included
+fn as_task(node: &Node, original: &str) -> Option<(Status, String)> {
+    let Node::ListItem(li) = node else {
+        return None;
+    };
+
+    let text = item_text(li, original);
+    match li.checked {
+        Some(true) => Some((Status::Done, text)),
+        Some(false) => Some((Status::Todo, text)),
+        None => {
+            // Markdown only knows ` ` and `x`, the rest is left as text
+            let caps = RE_STATUS.captures(&text)?;
+            let status = Status::from_mark(caps["mark"].chars().next()?);
+            Some((status, text[caps.get(0)?.end()..].to_string()))
+        },
+    }
+}
+
+
+/// Text of the paragraphs at the beginning of the list item
+fn item_text(li: &ListItem, original: &str) -> String {
+    let mut text = String::new();
+    for li_child in &li.children {
+        match li_child {
+            Node::Paragraph(
+                Paragraph {
+                    position: Some(position),
+                    ..
+                }
+            ) => {
+                let p_text = &original[position.start.offset..position.end.offset];
+                text.push_str(p_text);
+            },
+            _ => {
+                // Only extract paragraphs in the beginning.
+                break;
+            }
+        }
+    }
+
+    text
+}
+
+
+/// Tasks that are still open, the others are left out with their subtasks
+pub fn unchecked(tasks: Vec<Task>) -> Vec<Task> {
+    filter_tasks(tasks, &|task| task.status.is_open())
+}
+
+
+/// Tasks `keep` is true for, the others are left out with their subtasks
+pub fn filter_tasks(tasks: Vec<Task>, keep: &impl Fn(&Task) -> bool) -> Vec<Task> {
+    tasks.into_iter()
+        .filter(keep)
+        .map(|task| Task { subtasks: filter_tasks(task.subtasks, keep), ..task })
+        .collect()
+}
+
+
+/// Tasks with a time, wherever they are nested
+pub fn collect_timed<'t>(tasks: &'t [Task], timed: &mut Vec<&'t Task>) {
+    for task in tasks {
+        if task.time.is_some() {
+            timed.push(task);
+        }
+        collect_timed(&task.subtasks, timed);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tasks(note: &str) -> Vec<Task> {
+        collect_tasks(&note_to_ast(note), note).unwrap()
+    }
+
+    fn time(h: u32, m: u32, minutes: i64) -> Option<(NaiveTime, Duration)> {
+        Some((NaiveTime::from_hms_opt(h, m, 0).unwrap(), Duration::minutes(minutes)))
+    }
+
+    #[test]
+    fn nested_tasks() {
+        let note = "# Day\n\n- [ ] One\n\t- [x] Two\n\t- [ ] Three\n\t\t- [ ] Four\n- [x] Five\n";
+        let tasks = tasks(note);
+
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].text, "One");
+        assert_eq!(tasks[0].status, Status::Todo);
+        assert_eq!(tasks[0].subtasks.len(), 2);
+        assert_eq!(tasks[0].subtasks[0].text, "Two");
+        assert_eq!(tasks[0].subtasks[0].status, Status::Done);
+        assert_eq!(tasks[0].subtasks[1].subtasks[0].text, "Four");
+        assert_eq!(tasks[1].text, "Five");
+        assert_eq!(tasks[1].status, Status::Done);
+    }
+
+    #[test]
+    fn only_unchecked() {
+        let note = "- [ ] One\n\t- [x] Two\n\t- [ ] Three\n- [x] Four\n\t- [ ] Hidden with the parent\n";
+        let tasks = unchecked(tasks(note));
+
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].subtasks.len(), 1);
+        assert_eq!(tasks[0].subtasks[0].text, "Three");
+    }
+
+    #[test]
+    fn statuses() {
+        let note = "- [ ] Todo\n- [x] Done\n- [X] Done too\n- [/] Doing\n- [-] Cancelled\n- [>] Deferred\n- [!] Important\n- [?] Question\n- [~] Other\n- [[Link]] Not a task\n";
+        let tasks = tasks(note);
+
+        let statuses: Vec<_> = tasks.iter().map(|t| t.status).collect();
+        assert_eq!(statuses, [
+            Status::Todo, Status::Done, Status::Done, Status::InProgress, Status::Cancelled,
+            Status::Deferred, Status::Important, Status::Question, Status::Done,
+        ]);
+        assert_eq!(tasks[3].text, "Doing");
+
+        // Cancelled and deferred tasks don't need doing
+        let counts = TaskCounts::of(&tasks);
+        assert_eq!((counts.done, counts.total), (3, 7));
+
+        let open: Vec<_> = unchecked(tasks).iter().map(|t| t.text.clone()).collect();
+        assert_eq!(open, ["Todo", "Doing", "Important", "Question"]);
+    }
+
+    #[test]
+    fn headings_and_parents() {
+        let note = "# Day\n\n- [ ] Loose\n\n## Work\n\n- [ ] Report\n\t- [ ] Charts\n\n### Calls\n\n- Boss\n\t- [ ] Ask for leave\n\n## Home\n\n- [ ] Dishes\n";
+        let tasks = tasks(note);
+
+        assert_eq!(tasks[0].headings, ["Day"]);
+        assert_eq!(tasks[1].headings, ["Day", "Work"]);
+        assert_eq!(tasks[1].subtasks[0].headings, ["Day", "Work"]);
+        assert_eq!(tasks[2].headings, ["Day", "Work", "Calls"]);
+        assert_eq!(tasks[2].parent.as_deref(), Some("Boss"));
+        assert_eq!(tasks[3].headings, ["Day", "Home"]);
+        assert_eq!(tasks[3].parent, None);
+
+        assert!(tasks[2].is_under("work"));
+        assert!(tasks[2].is_under("Day > Calls"));
+        assert!(!tasks[2].is_under("Home"));
+        assert!(!tasks[2].is_under("Calls > Work"));
+        assert!(!tasks[0].is_under(""));
+    }
+
+    #[test]
+    fn tasks_under_plain_list_items() {
+        let note = "- Not a task\n\t- [ ] Task\n\nText\n\n> - [ ] Quoted task\n";
+        let tasks = tasks(note);
+
+        let texts: Vec<_> = tasks.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, ["Task", "Quoted task"]);
+    }
+
+    #[test]
+    fn time_fields() {
+        let note = "\
+- [ ] Hours and minutes [time:: 10:00 1h30m]
+- [ ] Hours [time:: 9:05 2h]
+- [ ] Minutes [time::14:30 45m]
+- [ ] No time
+";
+        let tasks = tasks(note);
+
+        assert_eq!(tasks[0].time, time(10, 0, 90));
+        assert_eq!(tasks[1].time, time(9, 5, 120));
+        assert_eq!(tasks[2].time, time(14, 30, 45));
+        assert_eq!(tasks[3].time, None);
+
+        // The field is removed from the text
+        assert_eq!(tasks[0].text, "Hours and minutes ");
+    }
+
+    #[test]
+    fn time_without_duration() {
+        let note = "\
+- [ ] Call mum [time:: 14:00]
+- [ ] Call dad [time::14:00 ]
+";
+        let tasks = tasks(note);
+
+        assert_eq!(tasks[0].time, time(14, 0, 0));
+        assert_eq!(tasks[1].time, time(14, 0, 0));
+        assert_eq!(tasks[0].text, "Call mum ");
+    }
+
+    #[test]
+    fn task_ends() {
+        let tasks = tasks("- [ ] Late show [time:: 23:00 2h]\n- [ ] No time\n");
+        let date = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+
+        // Past midnight, on the next day
+        assert_eq!(tasks[0].ends(date), date.succ_opt().unwrap().and_hms_opt(1, 0, 0));
+        assert_eq!(tasks[1].ends(date), None);
+    }
+
+    #[test]
+    fn malformed_time() {
+        let note = "- [ ] Too late [time:: 25:99 1h]\n";
+        assert!(collect_tasks(&note_to_ast(note), note).is_err());
+
+        // Not a time field at all, left in the text
+        let note = "- [ ] Sometime [time:: soon]\n";
+        let tasks = tasks(note);
+        assert_eq!(tasks[0].time, None);
+        assert_eq!(tasks[0].text, "Sometime [time:: soon]");
+    }
+
+    #[test]
+    fn malformed_notes() {
+        for note in ["", "- [ ]", "- [x]\n- [", "```\n- [ ] In code\n", "- [ ] Unclosed `code\n\t- [ ] Child"] {
+            collect_tasks(&note_to_ast(note), note).unwrap();
+        }
+    }
+
+    #[test]
+    fn fields() {
+        let note = "\
+water:: 6
+- exercise:: 1
+- [x] Run [distance:: 5 km] and (pace:: slow)
+Not:a field
+";
+        let fields = inline_fields(note);
+
+        assert_eq!(fields, [
+            ("water".to_string(), "6".to_string()),
+            ("exercise".to_string(), "1".to_string()),
+            ("distance".to_string(), "5 km".to_string()),
+            ("pace".to_string(), "slow".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn habits() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/vault");
+        let vault = Vault::new(&root);
+        let names = ["water".to_string(), "Meditate".to_string(), "exercise".to_string()];
+
+        let habits = vault.habits(&names, NaiveDate::from_ymd_opt(2026, 10, 18).unwrap(), 7).unwrap();
+
+        // 12th to 18th, with the notes of the 14th and the 12th missing
+        assert_eq!(habits[0].values, [0.0, 5.0, 0.0, 2.0, 3.0, 8.0, 6.0]);
+        assert_eq!(habits[1].values, [0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+        assert_eq!(habits[2].values, [0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0]);
+
+        assert_eq!(habits[0].streak(), 4);
+        assert_eq!(habits[1].streak(), 3);
+        // Today isn't over yet
+        assert_eq!(habits[2].streak(), 2);
+    }
+
+    #[test]
+    fn sections() {
+        let note = "\
+# Day
+
+## Plan
+
+Intro
+
+### Morning
+
+- [ ] Run
+
+#### Details
+
+Stretch first
+
+### Evening
+
+Read
+
+## Notes
+
+> Quote
+";
+        let ast = note_to_ast(note);
+        let texts = |path: &str| section(&ast, path).map(|s| s.blocks.iter().map(|b| b.to_string()).collect::<Vec<_>>());
+
+        assert_eq!(section(&ast, "plan > morning").unwrap().title, "Morning");
+        assert_eq!(texts("Plan > Morning").unwrap(), ["Run", "Details", "Stretch first"]);
+        assert_eq!(texts("## Plan").unwrap().len(), 7);
+        assert_eq!(texts("Day > Evening").unwrap(), ["Read"]);
+        assert_eq!(texts("Notes").unwrap(), ["Quote"]);
+        assert_eq!(texts("Notes > Morning"), None);
+        assert_eq!(section(&ast, "Plan").unwrap().tasks(note).unwrap().len(), 1);
+        assert_eq!(texts("Lunch"), None);
+        assert_eq!(texts(""), None);
+    }
+
+    #[test]
+    fn properties() {
+        let note = "\
+---
+mood: 4
+sleep: 7.5
+focus: \"ship: release\"
+reviewed: false
+date: 2026-10-18
+started: 2026-10-18T08:30
+tags:
+  - work
+  - \"deep\"
+aliases: [Sunday, 'day off']
+empty:
+---
+# Day
+
+- [ ] Task
+";
+        let note = Note::parse(note).unwrap();
+
+        assert_eq!(note.property("mood"), Some(&Property::Number(4.0)));
+        assert_eq!(note.property("Sleep"), Some(&Property::Number(7.5)));
+        assert_eq!(note.property("focus"), Some(&Property::Text("ship: release".to_string())));
+        assert_eq!(note.property("reviewed"), Some(&Property::Checkbox(false)));
+        assert_eq!(note.property("date"), Some(&Property::Date(NaiveDate::from_ymd_opt(2026, 10, 18).unwrap())));
+        assert_eq!(note.property("started").unwrap().to_string(), "2026-10-18 08:30");
+        assert_eq!(note.property("tags"), Some(&Property::List(vec!["work".to_string(), "deep".to_string()])));
+        assert_eq!(note.property("aliases").unwrap().to_string(), "Sunday, day off");
+        assert_eq!(note.property("empty"), Some(&Property::Empty));
+        assert_eq!(note.property("missing"), None);
+
+        // The frontmatter is not taken for content
+        assert_eq!(note.tasks.len(), 1);
+        assert_eq!(note_to_ast("---\nmood: 4\n---\n").children().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn no_properties() {
+        let note = Note::parse("# Day\n\n---\n\nmood: 4\n").unwrap();
+        assert!(note.properties.is_empty());
+
+        let note = Note::parse("---\nmood: 4\n").unwrap();
+        assert!(note.properties.is_empty());
+    }
+
+    #[test]
+    fn task_counts() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/vault");
+        let vault = Vault::new(&root);
+        let date = NaiveDate::from_ymd_opt(2026, 10, 20).unwrap();
+
+        let config = vault.config().unwrap();
+        assert_eq!(vault.task_counts(&config, date).unwrap(), Some(TaskCounts { done: 1, total: 3 }));
+        assert_eq!(vault.task_counts(&config, date.pred_opt().unwrap()).unwrap(), None);
+
+        assert_eq!(vault.note_in(&config, date).unwrap().map(|note| note.tasks.len()), Some(3));
+        assert!(vault.note_in(&config, date.pred_opt().unwrap()).unwrap().is_none());
+
+        let counts = TaskCounts::of(&tasks("- [ ] One\n\t- [x] Two\n- [x] Three\n"));
+        assert_eq!((counts.done, counts.open()), (2, 1));
+    }
+
+    #[test]
+    fn note_paths() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/vault");
+        let vault = Vault::new(&root);
+        let date = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+
+        assert_eq!(vault.note_path(date).unwrap(), root.join("Periodic/2026/20261018.md"));
+        assert!(vault.read_note(date).unwrap().contains("Write the report"));
+        assert_eq!(
+            vault.read_note(date.succ_opt().unwrap()).unwrap_err().to_string(),
+            "Vault: No note for 2026-10-19 (Periodic/2026/20261019.md)",
+        );
+    }
+
+    #[test]
+    fn append_note() {
+        let root = std::env::temp_dir().join(format!("pinterface-append-{}", std::process::id()));
+        std::fs::create_dir_all(root.join(".obsidian/plugins/ftvkyo")).unwrap();
+        std::fs::write(root.join(".obsidian/plugins/ftvkyo/data.json"), r#"{"folderPeriodic": ""}"#).unwrap();
+
+        let vault = Vault::new(&root);
+        let date = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+
+        // A missing note is created, an unfinished last line is not continued
+        vault.append_note(date, "- One").unwrap();
+        assert_eq!(vault.read_note(date).unwrap(), "- One\n");
+
+        std::fs::write(vault.note_path(date).unwrap(), "- One\n- Two").unwrap();
+        vault.append_note(date, "- Three").unwrap();
+
+        assert_eq!(vault.read_note(date).unwrap(), "- One\n- Two\n- Three\n");
+
+        // Folders of the note that are not there yet are created
+        std::fs::write(root.join(".obsidian/plugins/ftvkyo/data.json"), r#"{"folderPeriodic": "Periodic/2026/10", "groupByYear": true}"#).unwrap();
+        vault.append_note(date, "- Four").unwrap();
+
+        assert_eq!(vault.note_path(date).unwrap(), root.join("Periodic/2026/10/2026/20261018.md"));
+        assert_eq!(vault.read_note(date).unwrap(), "- Four\n");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn vault_is_not_set() {
+        let vault = Vault { root: None };
+        let date = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+
+        assert_eq!(vault.note_path(date).unwrap_err().to_string(), "Vault: VAULT is not set");
+    }
+}